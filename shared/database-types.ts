@@ -7,6 +7,18 @@
 
 // === New Database Models (Phase 1 Core) ===
 
+// Sampling settings sent with each chat request; unset fields use the provider default
+export interface GenerationParams {
+  temperature?: number; // 0 to 2
+  top_p?: number; // Greater than 0, at most 1
+  max_tokens?: number;
+  stop?: string[]; // At most 4 sequences
+  seed?: number;
+  extras?: Record<string, unknown>; // Provider-specific request fields
+}
+
+export type SessionStatus = 'open' | 'closed' | 'archived';
+
 export interface Session {
   id: number;
   name: string;
@@ -14,18 +26,164 @@ export interface Session {
   goals?: string;
   llm_provider?: string;
   model_id?: string;
-  status: string; // 'open', 'closed', etc.
+  status: SessionStatus; // Only open sessions accept message changes
+  created_at: number; // Unix timestamp
+  active_leaf_id?: number; // Last message of the active branch
+  forked_from_session_id?: number; // Source session when this is a fork
+  forked_from_message_id?: number; // Last message copied from the source
+  deleted_at?: number; // Unix timestamp when moved to the trash
+  folder_id?: number;
+  pinned: boolean;
+  tags: string[];
+  include_reasoning: boolean; // Send stored reasoning back in prompt context
+  generation_params: GenerationParams;
+  persona_id?: number; // Persona the session was started from
+}
+
+// Reusable persona; sessions copy its settings and keep a reference to it
+export interface Persona {
+  id: number;
+  name: string;
+  system_prompt?: string; // Becomes the session's role
+  goals?: string;
+  llm_provider?: string;
+  model_id?: string;
+  generation_params: GenerationParams;
+  avatar?: string; // Emoji, image URL or attachment path
+  created_at: number; // Unix timestamp
+  updated_at: number; // Unix timestamp
+}
+
+export interface CreatePersona {
+  name: string;
+  system_prompt?: string;
+  goals?: string;
+  llm_provider?: string;
+  model_id?: string;
+  generation_params?: GenerationParams;
+  avatar?: string;
+}
+
+export interface SessionSummary extends Session {
+  message_count: number;
+  last_message_at?: number; // Unix timestamp in milliseconds
+  last_message_preview?: string;
+  total_tokens: number;
+}
+
+export interface SessionQuery {
+  folder_id?: number;
+  tag?: string;
+  pinned?: boolean;
+  include_archived?: boolean;
+  sort?: 'last_activity' | 'name' | 'created_at'; // Defaults to 'last_activity'
+  pinned_first?: boolean; // Defaults to true
+}
+
+export interface Folder {
+  id: number;
+  name: string;
   created_at: number; // Unix timestamp
 }
 
+export type MessageRole = 'user' | 'assistant' | 'system' | 'tool' | 'developer';
+
+export type ContentPart =
+  | { type: 'text'; text: string }
+  | { type: 'image'; url: string; mime_type?: string; alt?: string }
+  | { type: 'tool_call'; id: string; name: string; arguments: unknown }
+  | { type: 'tool_result'; tool_call_id: string; content: string; is_error?: boolean }
+  | { type: 'reasoning'; text: string };
+
 export interface Message {
   id: number;
   session_id: number;
-  role: string; // 'user' | 'assistant' | 'system'
+  role: MessageRole;
   content: string;
   ts: number; // Unix timestamp
+  ts_ms: number; // Unix timestamp in milliseconds
   embedding?: number[]; // Vector embedding for semantic search
   recall_score?: number;
+  parent_id?: number; // Previous message in the conversation tree
+  deleted_at?: number; // Unix timestamp when moved to the trash
+  token_count?: number;
+  content_parts?: ContentPart[]; // Structured content; content holds its plain text
+  reasoning_tokens?: number; // Share of token_count spent on reasoning
+  generation_params?: GenerationParams; // Parameters a reply was generated with
+}
+
+// Payload of the `chat-stream` event emitted while a reply is generated
+export type ChatStreamEvent = { session_id: number } & (
+  | { type: 'content'; text: string }
+  | { type: 'reasoning'; text: string }
+  | { type: 'usage'; prompt_tokens?: number; completion_tokens?: number; reasoning_tokens?: number }
+);
+
+export interface MessageRevision {
+  id: number;
+  message_id: number;
+  content: string; // Content before the change
+  content_parts?: ContentPart[];
+  changed_by?: string; // 'user', 'regenerate', 'restore', ...
+  revised_at: number; // Unix timestamp
+}
+
+export interface MessagePage {
+  messages: Message[]; // Newest first, ordered by (ts_ms, id)
+  has_more: boolean;
+}
+
+// Markdown and HTML render the active branch; JSON keeps every branch losslessly
+export type ExportFormat = 'markdown' | 'json' | 'html';
+
+// Archives accepted by `import_conversations`; conversations imported before are skipped
+export type ImportSource = 'chatgpt' | 'claude';
+
+// Payload of the `import-progress` event emitted after each conversation
+export interface ImportProgress {
+  source: ImportSource;
+  processed: number;
+  total: number;
+  imported: number;
+  skipped: number; // Already imported earlier
+}
+
+export interface ImportReport {
+  imported: number;
+  skipped: number;
+  messages: number;
+  session_ids: number[];
+}
+
+export type BackupKind = 'manual' | 'daily' | 'weekly' | 'pre_restore';
+
+// Snapshot written with `VACUUM INTO`; scheduled ones rotate, manual ones are kept
+export interface BackupInfo {
+  path: string;
+  kind: BackupKind;
+  created_at: number; // Unix timestamp
+  size_bytes: number;
+}
+
+// Stored under `memory_config.config.backup` in the settings
+export interface BackupSchedule {
+  enabled?: boolean; // Defaults to false
+  daily?: number; // Daily snapshots kept, defaults to 7
+  weekly?: number; // Weekly snapshots kept, defaults to 4
+  directory?: string; // Defaults to `backups/` next to the database
+}
+
+// Error thrown by init_database and the encryption commands
+export interface DatabaseCommandError {
+  code: 'locked' | 'wrong_passphrase' | 'database_error';
+  message: string;
+}
+
+// Payload of the `database-status` event and of `get_database_status`
+export interface DatabaseStatus {
+  state: 'ready' | 'locked' | 'error' | 'uninitialized';
+  path?: string;
+  message?: string;
 }
 
 export interface DatabaseStats {
@@ -33,6 +191,42 @@ export interface DatabaseStats {
   message_count: number;
   database_size_bytes?: number;
   vector_index_size?: number;
+  page_size: number;
+  page_count: number;
+  freelist_count: number; // Unused pages, reclaimed by vacuum_database
+  reclaimable_bytes: number;
+  wal_size_bytes?: number;
+  table_row_counts: Record<string, number>; // Every row, including trashed ones
+}
+
+export interface IntegrityReport {
+  ok: boolean;
+  problems: string[];
+  foreign_key_violations: number;
+  duration_ms: number;
+}
+
+export interface VacuumReport {
+  size_before_bytes: number;
+  size_after_bytes: number;
+  reclaimed_bytes: number;
+  duration_ms: number;
+}
+
+export interface AnalyzeReport {
+  tables_analyzed: number;
+  duration_ms: number;
+}
+
+export type CheckpointMode = 'passive' | 'full' | 'restart' | 'truncate';
+
+export interface CheckpointReport {
+  mode: CheckpointMode;
+  busy: boolean; // A reader or writer kept the checkpoint from finishing
+  wal_frames: number; // -1 when the database is not in WAL mode
+  checkpointed_frames: number;
+  wal_size_before_bytes?: number;
+  wal_size_after_bytes?: number;
 }
 
 // === Input Types for Creating Records ===
@@ -43,15 +237,17 @@ export interface CreateSession {
   goals?: string;
   llm_provider?: string;
   model_id?: string;
-  status?: string;
+  status?: SessionStatus;
+  persona_id?: number; // Unset fields are filled from the persona
 }
 
 export interface CreateMessage {
   session_id: number;
-  role: string;
+  role: MessageRole;
   content: string;
   embedding?: number[];
   recall_score?: number;
+  content_parts?: ContentPart[]; // When set, content is derived from the parts
 }
 
 // === Legacy Database Models (Deprecated) ===
@@ -20,6 +20,7 @@ export interface ChatMessage {
   sender: 'user' | 'ai' | 'system';
   content: string;
   timestamp: number;
+  reasoning?: string; // Model's thinking, shown apart from the answer
 }
 
 /**
@@ -48,6 +49,9 @@ export function messageToChatMessage(message: Message): ChatMessage {
     sender: message.role === 'assistant' ? 'ai' : (message.role as 'user' | 'system'),
     content: message.content,
     timestamp: message.ts * 1000, // Convert Unix timestamp to JS timestamp
+    reasoning: message.content_parts
+      ?.flatMap((part) => (part.type === 'reasoning' ? [part.text] : []))
+      .join('\n\n') || undefined,
   };
 }
 
@@ -5,13 +5,34 @@ export interface ChatMessage {
   sender: 'user' | 'ai' | 'system';
   content: string;
   timestamp: number;
+  reasoning?: string; // Model's thinking, shown apart from the answer
 }
 
 // Settings types
+// Where all data is kept; see get_data_directory
+export interface DataDirInfo {
+  path: string;
+  source: 'environment' | 'configured' | 'default'; // environment = OPENCONV_HOME
+}
+
+// A workspace with its own database, attachments and provider settings
+export interface WorkspaceInfo {
+  id: string;
+  name: string;
+  created_at: number; // Unix timestamp
+  active: boolean;
+  db_path: string;
+  attachments_dir: string;
+}
+
 export interface MemoryConfig {
   provider: 'sqlite' | 'supabase';
   config: {
-    // SQLite config (empty for now)
+    path?: string; // SQLite database file, defaults to db/conv.db in the active workspace
+    // SQLite connection pool
+    max_connections?: number; // Defaults to 5
+    min_connections?: number; // Defaults to 0
+    busy_timeout_ms?: number; // Defaults to 5000
     // Supabase config
     projectUrl?: string;
     apiKey?: string;
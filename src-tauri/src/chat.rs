@@ -0,0 +1,160 @@
+//! Chat completions for a session, streamed to the frontend from the Rust side
+
+use crate::connectors::openrouter::{chat_request, system_message, to_chat_message, OpenRouterConnector};
+use crate::connectors::StreamEvent;
+use crate::database::commands::{database, DatabaseState};
+use crate::database::models::{ContentPart, CreateMessage, Message, MessageRole};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+/// Event emitted with a `ChatStreamPayload` for every streamed increment
+pub const CHAT_STREAM_EVENT: &str = "chat-stream";
+
+/// Provider used when a session does not name one
+const DEFAULT_PROVIDER: &str = "openrouter";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatStreamPayload {
+    pub session_id: i64,
+    #[serde(flatten)]
+    pub event: StreamEvent,
+}
+
+#[derive(Deserialize)]
+pub struct SendChatMessageParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    pub content: String,
+}
+
+/// Save a user message, stream the model's reply and save it as the assistant message
+///
+/// The provider, API key and the session's generation parameters are checked,
+/// the latter against the model's limits when they are known, before anything
+/// is saved, so a rejected request leaves no unanswered user turn. The session's
+/// role and goals open the transcript as a system message. The reply is saved as
+/// the child of the user message and records the generation parameters. Answer
+/// text and reasoning are emitted as separate `chat-stream` events. The saved
+/// reply keeps reasoning as its own content part with its token count, and
+/// reasoning is only sent back to the model when the session asks for it.
+#[tauri::command]
+pub async fn send_chat_message(
+    app: AppHandle,
+    params: SendChatMessageParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Message, String> {
+    let session_id = params.session_id;
+
+    // One handle for the whole exchange, so the reply is written to the same
+    // database as the user message even if a workspace switch or data directory
+    // move reopens the shared one while the reply streams
+    let manager = database(&state).await?;
+    let repo = manager.memory_repo();
+
+    let session = repo
+        .get_session_by_id(session_id)
+        .await
+        .map_err(|e| format!("Failed to get session: {}", e))?;
+
+    let model = session
+        .model_id
+        .clone()
+        .ok_or("Session has no model selected")?;
+    let provider_id = session.llm_provider.as_deref().unwrap_or(DEFAULT_PROVIDER);
+    let provider = settings::load_settings()?
+        .providers
+        .into_iter()
+        .find(|provider| provider.id == provider_id && provider.enabled != Some(false))
+        .ok_or_else(|| format!("Provider {} is not configured", provider_id))?;
+    let api_key = provider
+        .api_key
+        .ok_or_else(|| format!("Provider {} has no API key", provider_id))?;
+
+    let generation_params = session.generation_params.clone();
+    generation_params.validate().map_err(|e| e.to_string())?;
+    if let Some(limits) = OpenRouterConnector
+        .model_limits(&api_key, provider.base_url.as_deref(), &model)
+        .await
+    {
+        limits.check(&model, &generation_params).map_err(|e| e.to_string())?;
+    }
+
+    let user_message = repo
+        .save_message(CreateMessage {
+            session_id,
+            role: MessageRole::User,
+            content: params.content,
+            embedding: None,
+            recall_score: None,
+            parent_id: None,
+            token_count: None,
+            content_parts: None,
+            reasoning_tokens: None,
+            generation_params: None,
+        })
+        .await
+        .map_err(|e| format!("Failed to save message: {}", e))?;
+    let transcript = repo
+        .transcript(user_message.id)
+        .await
+        .map_err(|e| format!("Failed to load transcript: {}", e))?;
+
+    let messages: Vec<_> = system_message(&session)
+        .into_iter()
+        .chain(
+            transcript
+                .iter()
+                .map(|message| to_chat_message(message, session.include_reasoning)),
+        )
+        .collect();
+
+    let mut answer = String::new();
+    let mut reasoning = String::new();
+    let mut completion_tokens = None;
+    let mut reasoning_tokens = None;
+
+    // Every provider is reached through its OpenAI-compatible endpoint
+    OpenRouterConnector
+        .stream_chat(
+            &api_key,
+            provider.base_url.as_deref(),
+            chat_request(&model, messages, &generation_params),
+            |event| {
+                match &event {
+                    StreamEvent::Content { text } => answer.push_str(text),
+                    StreamEvent::Reasoning { text } => reasoning.push_str(text),
+                    StreamEvent::Usage { completion_tokens: completion, reasoning_tokens: thinking, .. } => {
+                        completion_tokens = *completion;
+                        reasoning_tokens = *thinking;
+                    }
+                }
+                let _ = app.emit(CHAT_STREAM_EVENT, ChatStreamPayload { session_id, event });
+            },
+        )
+        .await
+        .map_err(|e| format!("Chat request failed: {}", e))?;
+
+    let mut content_parts = Vec::new();
+    if !reasoning.is_empty() {
+        content_parts.push(ContentPart::Reasoning { text: reasoning });
+    }
+    content_parts.push(ContentPart::Text { text: answer });
+
+    repo
+        .save_message(CreateMessage {
+            session_id,
+            role: MessageRole::Assistant,
+            content: String::new(),
+            embedding: None,
+            recall_score: None,
+            // Not the active leaf, which may have moved on while the reply streamed
+            parent_id: Some(user_message.id),
+            token_count: completion_tokens,
+            content_parts: Some(content_parts),
+            reasoning_tokens,
+            generation_params: Some(generation_params),
+        })
+        .await
+        .map_err(|e| format!("Failed to save reply: {}", e))
+}
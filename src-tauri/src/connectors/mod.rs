@@ -7,8 +7,60 @@ pub use openrouter::OpenRouterConnector;
 pub use settings::SettingsManager;
 
 use async_trait::async_trait;
+use serde::Serialize;
 use std::collections::HashMap;
-use crate::database::Result;
+use crate::database::models::GenerationParams;
+use crate::database::{DatabaseError, Result};
+
+/// One increment of a streamed chat completion
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Answer text
+    Content { text: String },
+    /// Reasoning/thinking text, kept apart from the answer
+    Reasoning { text: String },
+    /// Token usage reported at the end of the stream
+    Usage {
+        prompt_tokens: Option<i64>,
+        completion_tokens: Option<i64>,
+        reasoning_tokens: Option<i64>,
+    },
+}
+
+/// Limits a provider reports for a model; `None` means not reported
+#[derive(Debug, Clone, Default)]
+pub struct ModelLimits {
+    pub context_length: Option<i64>,
+    pub max_completion_tokens: Option<i64>,
+    pub supported_parameters: Option<Vec<String>>,
+}
+
+impl ModelLimits {
+    /// Reject parameters the model does not accept or that exceed its output limit
+    pub fn check(&self, model: &str, params: &GenerationParams) -> Result<()> {
+        if let Some(supported) = &self.supported_parameters {
+            if let Some(name) = params.requested().into_iter().find(|name| !supported.iter().any(|s| s == name)) {
+                return Err(DatabaseError::InvalidGenerationParams(format!(
+                    "{} does not support {}",
+                    model, name
+                )));
+            }
+        }
+
+        let limit = self.max_completion_tokens.or(self.context_length);
+        if let (Some(max_tokens), Some(limit)) = (params.max_tokens, limit) {
+            if max_tokens > limit {
+                return Err(DatabaseError::InvalidGenerationParams(format!(
+                    "max_tokens {} exceeds the {} token limit of {}",
+                    max_tokens, limit, model
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
 
 #[async_trait]
 pub trait Connector: Send + Sync {
@@ -1,12 +1,166 @@
 //! OpenRouter connector implementation
 
-use super::Connector;
+use super::{Connector, ModelLimits, StreamEvent};
 use async_trait::async_trait;
 use std::collections::HashMap;
-use crate::database::Result;
+use std::sync::{Mutex, OnceLock};
+use crate::database::models::{ContentPart, GenerationParams, Message, MessageRole, Session};
+use crate::database::{DatabaseError, Result};
+use serde_json::{json, Value};
 
 pub struct OpenRouterConnector;
 
+const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// Limits already looked up, keyed by model and base URL
+type LimitsCache = Mutex<HashMap<(String, String), Option<ModelLimits>>>;
+
+fn limits_cache() -> &'static LimitsCache {
+    static CACHE: OnceLock<LimitsCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+impl OpenRouterConnector {
+    /// Look up a model's limits in the provider's model list
+    ///
+    /// Returns `None` when the limits are unknown: the provider does not list the
+    /// model, or the list could not be loaded. A failed lookup is logged and tried
+    /// again next time; anything else is cached for the model and base URL.
+    pub async fn model_limits(&self, api_key: &str, base_url: Option<&str>, model: &str) -> Option<ModelLimits> {
+        let base_url = base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/');
+        let key = (model.to_string(), base_url.to_string());
+        if let Some(limits) = limits_cache().lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            return limits.clone();
+        }
+
+        match self.fetch_model_limits(api_key, base_url, model).await {
+            Ok(limits) => {
+                limits_cache()
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(key, limits.clone());
+                limits
+            }
+            Err(e) => {
+                eprintln!("[OpenRouter] Limits for {} unavailable, not checking them: {}", model, e);
+                None
+            }
+        }
+    }
+
+    async fn fetch_model_limits(&self, api_key: &str, base_url: &str, model: &str) -> Result<Option<ModelLimits>> {
+        let res = reqwest::Client::new()
+            .get(format!("{}/models", base_url))
+            .bearer_auth(api_key)
+            .send()
+            .await
+            .map_err(|e| DatabaseError::Connection(format!("HTTP error: {}", e)))?;
+        if !res.status().is_success() {
+            return Err(DatabaseError::Connection(format!("Model list returned {}", res.status())));
+        }
+
+        let models: Value = res
+            .json()
+            .await
+            .map_err(|e| DatabaseError::Connection(format!("Invalid model list: {}", e)))?;
+        let Some(entry) = models["data"]
+            .as_array()
+            .and_then(|data| data.iter().find(|entry| entry["id"] == model))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(ModelLimits {
+            context_length: entry["context_length"].as_i64(),
+            max_completion_tokens: entry["top_provider"]["max_completion_tokens"].as_i64(),
+            supported_parameters: entry["supported_parameters"].as_array().map(|params| {
+                params.iter().filter_map(|p| p.as_str().map(str::to_string)).collect()
+            }),
+        }))
+    }
+
+    /// Stream a chat completion, passing each increment to `on_event`
+    ///
+    /// `body` is an OpenAI-style request; streaming and usage reporting are turned on
+    /// here. Reasoning arrives in `delta.reasoning`, or `delta.reasoning_content`
+    /// for some upstream providers, and is reported separately from the answer.
+    pub async fn stream_chat<F>(
+        &self,
+        api_key: &str,
+        base_url: Option<&str>,
+        mut body: Value,
+        mut on_event: F,
+    ) -> Result<()>
+    where
+        F: FnMut(StreamEvent),
+    {
+        body["stream"] = json!(true);
+        body["usage"] = json!({ "include": true });
+
+        let url = format!("{}/chat/completions", base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/'));
+        let mut res = reqwest::Client::new()
+            .post(url)
+            .bearer_auth(api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| DatabaseError::Connection(format!("HTTP error: {}", e)))?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            return Err(DatabaseError::Connection(format!("OpenRouter returned {}: {}", status, text)));
+        }
+
+        // Server-sent events; chunks can end mid-line, so only complete lines are parsed
+        let mut buffer = Vec::new();
+        while let Some(chunk) = res
+            .chunk()
+            .await
+            .map_err(|e| DatabaseError::Connection(format!("HTTP error: {}", e)))?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                // Lines without data, such as keep-alive comments, are skipped
+                let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data == "[DONE]" {
+                    return Ok(());
+                }
+
+                let chunk: Value = serde_json::from_str(data)
+                    .map_err(|e| DatabaseError::Connection(format!("Invalid stream chunk: {}", e)))?;
+                if let Some(error) = chunk.get("error") {
+                    return Err(DatabaseError::Connection(format!("OpenRouter stream error: {}", error)));
+                }
+
+                let delta = &chunk["choices"][0]["delta"];
+                let reasoning = delta["reasoning"].as_str().or(delta["reasoning_content"].as_str());
+                if let Some(text) = reasoning.filter(|text| !text.is_empty()) {
+                    on_event(StreamEvent::Reasoning { text: text.to_string() });
+                }
+                if let Some(text) = delta["content"].as_str().filter(|text| !text.is_empty()) {
+                    on_event(StreamEvent::Content { text: text.to_string() });
+                }
+
+                let usage = &chunk["usage"];
+                if usage.is_object() {
+                    on_event(StreamEvent::Usage {
+                        prompt_tokens: usage["prompt_tokens"].as_i64(),
+                        completion_tokens: usage["completion_tokens"].as_i64(),
+                        reasoning_tokens: usage["completion_tokens_details"]["reasoning_tokens"].as_i64(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Connector for OpenRouterConnector {
     async fn test_settings(&self, settings: &HashMap<String, String>) -> Result<bool> {
@@ -27,3 +181,87 @@ impl Connector for OpenRouterConnector {
         "openrouter"
     }
 }
+
+/// Build a chat completion request body with the session's generation parameters
+pub fn chat_request(model: &str, messages: Vec<Value>, params: &GenerationParams) -> Value {
+    let mut body = json!({ "model": model, "messages": messages });
+    // Validation keeps extras from overriding the fields set below
+    if let Some(extras) = &params.extras {
+        for (key, value) in extras {
+            body[key] = value.clone();
+        }
+    }
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = params.top_p {
+        body["top_p"] = json!(top_p);
+    }
+    if let Some(max_tokens) = params.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+    if let Some(stop) = &params.stop {
+        body["stop"] = json!(stop);
+    }
+    if let Some(seed) = params.seed {
+        body["seed"] = json!(seed);
+    }
+    body
+}
+
+/// System message carrying the session's role and goals, if it has either
+pub fn system_message(session: &Session) -> Option<Value> {
+    let role = session.role.as_deref().filter(|role| !role.trim().is_empty());
+    let goals = session.goals.as_deref().filter(|goals| !goals.trim().is_empty());
+    let prompt = match (role, goals) {
+        (None, None) => return None,
+        (Some(role), None) => role.to_string(),
+        (None, Some(goals)) => format!("Your goals: {}", goals),
+        (Some(role), Some(goals)) => format!("{}\nYour goals: {}", role, goals),
+    };
+    Some(json!({ "role": "system", "content": prompt }))
+}
+
+/// Convert a stored message to the OpenAI-style chat format used by OpenRouter
+///
+/// Text and images become content blocks, tool calls go to `tool_calls` and a
+/// tool message carries its `tool_call_id`. Reasoning is sent as `reasoning`
+/// only when `include_reasoning` is set.
+pub fn to_chat_message(message: &Message, include_reasoning: bool) -> Value {
+    let mut content = Vec::new();
+    let mut tool_calls = Vec::new();
+    let mut reasoning = Vec::new();
+    let mut tool_call_id = None;
+
+    for part in message.parts() {
+        match part {
+            ContentPart::Text { text } => content.push(json!({ "type": "text", "text": text })),
+            ContentPart::Image { url, .. } => {
+                content.push(json!({ "type": "image_url", "image_url": { "url": url } }))
+            }
+            ContentPart::ToolCall { id, name, arguments } => tool_calls.push(json!({
+                "id": id,
+                "type": "function",
+                "function": { "name": name, "arguments": arguments.to_string() },
+            })),
+            ContentPart::ToolResult { tool_call_id: id, content: result, .. } => {
+                tool_call_id = Some(id);
+                content.push(json!({ "type": "text", "text": result }));
+            }
+            ContentPart::Reasoning { text } if include_reasoning => reasoning.push(text),
+            ContentPart::Reasoning { .. } => {}
+        }
+    }
+
+    let mut chat = json!({ "role": message.role.as_str(), "content": content });
+    if !tool_calls.is_empty() {
+        chat["tool_calls"] = Value::Array(tool_calls);
+    }
+    if !reasoning.is_empty() {
+        chat["reasoning"] = Value::String(reasoning.join("\n\n"));
+    }
+    if message.role == MessageRole::Tool {
+        chat["tool_call_id"] = json!(tool_call_id);
+    }
+    chat
+}
@@ -1,4 +1,4 @@
-//! Settings manager for ~/.openconv/settings
+//! Settings manager for the `settings` entry in the active workspace
 
 use std::collections::HashMap;
 use std::fs;
@@ -11,9 +11,9 @@ pub struct SettingsManager {
 
 impl SettingsManager {
     pub fn new() -> Self {
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let path = PathBuf::from(home).join(".openconv").join("settings");
-        Self { path }
+        Self {
+            path: crate::workspaces::active_workspace().dir().join("settings"),
+        }
     }
 
     pub fn load(&self) -> io::Result<HashMap<String, String>> {
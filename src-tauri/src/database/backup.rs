@@ -0,0 +1,305 @@
+//! Online backups, scheduled snapshots and restore of the database file
+//!
+//! Snapshots are taken with `VACUUM INTO` while the app keeps running, so they
+//! are always consistent. Each file is named `conv-<kind>-<UTC time>.db`; the
+//! scheduler keeps a rolling set of daily and weekly snapshots and never touches
+//! manual or pre-restore ones. A restore only swaps in a file that passes
+//! `PRAGMA integrity_check`. Encrypting the database or changing its passphrase
+//! re-keys the backups too, so they can still be restored afterwards.
+
+use crate::database::commands::DatabaseState;
+use crate::database::providers::sqlite::SqliteProvider;
+use crate::database::{encryption, DatabaseError, DatabaseManager, Result};
+use crate::settings;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const FILE_PREFIX: &str = "conv-";
+const FILE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How often the scheduler checks whether a snapshot is due
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+const WEEK_SECS: i64 = 7 * DAY_SECS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+    Manual,
+    Daily,
+    Weekly,
+    PreRestore, // Taken automatically before a restore replaces the database
+}
+
+impl BackupKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupKind::Manual => "manual",
+            BackupKind::Daily => "daily",
+            BackupKind::Weekly => "weekly",
+            BackupKind::PreRestore => "pre_restore",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "manual" => Some(BackupKind::Manual),
+            "daily" => Some(BackupKind::Daily),
+            "weekly" => Some(BackupKind::Weekly),
+            "pre_restore" => Some(BackupKind::PreRestore),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub kind: BackupKind,
+    pub created_at: i64, // Unix timestamp
+    pub size_bytes: u64,
+}
+
+/// Scheduled snapshot settings, read from `memory_config.config.backup`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSchedule {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_daily")]
+    pub daily: usize, // Daily snapshots kept
+    #[serde(default = "default_weekly")]
+    pub weekly: usize, // Weekly snapshots kept
+    pub directory: Option<String>, // Defaults to `backups/` next to the database
+}
+
+fn default_daily() -> usize {
+    7
+}
+
+fn default_weekly() -> usize {
+    4
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            daily: default_daily(),
+            weekly: default_weekly(),
+            directory: None,
+        }
+    }
+}
+
+impl BackupSchedule {
+    /// Schedule from the saved settings; disabled when missing or unreadable
+    pub fn from_settings() -> Self {
+        settings::load_settings()
+            .ok()
+            .and_then(|settings| settings.memory_config.pointer("/config/backup").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn dir(&self, db_path: &Path) -> PathBuf {
+        self.directory.as_ref().map(PathBuf::from).unwrap_or_else(|| backups_dir_for(db_path))
+    }
+}
+
+/// Directory backups of `db_path` are written to unless another one is given
+pub fn backups_dir_for(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .map(|dir| dir.join("backups"))
+        .unwrap_or_else(|| PathBuf::from("backups"))
+}
+
+/// Snapshot the open database into `dir`
+pub async fn create_backup(manager: &DatabaseManager, dir: &Path, kind: BackupKind) -> Result<BackupInfo> {
+    let now = Utc::now();
+    let path = dir.join(format!(
+        "{}{}-{}.db",
+        FILE_PREFIX,
+        kind.as_str(),
+        now.format(FILE_TIME_FORMAT)
+    ));
+    manager.backup_to(&path).await?;
+
+    Ok(BackupInfo {
+        size_bytes: fs::metadata(&path)?.len(),
+        path,
+        kind,
+        created_at: now.timestamp(),
+    })
+}
+
+/// Backups found in `dir`, newest first; a missing directory has none
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some((kind, created_at)) = parse_file_name(&path) else { continue };
+        backups.push(BackupInfo {
+            size_bytes: fs::metadata(&path)?.len(),
+            path,
+            kind,
+            created_at,
+        });
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.path.cmp(&a.path)));
+
+    Ok(backups)
+}
+
+fn parse_file_name(path: &Path) -> Option<(BackupKind, i64)> {
+    if path.extension()? != "db" {
+        return None;
+    }
+    let (kind, time) = path.file_stem()?.to_str()?.strip_prefix(FILE_PREFIX)?.rsplit_once('-')?;
+    let created_at = NaiveDateTime::parse_from_str(time, FILE_TIME_FORMAT).ok()?.and_utc().timestamp();
+    Some((BackupKind::parse(kind)?, created_at))
+}
+
+/// Take the daily and weekly snapshots that are due, then drop the oldest beyond retention
+pub async fn run_scheduled_backups(manager: &DatabaseManager, schedule: &BackupSchedule) -> Result<Vec<BackupInfo>> {
+    let db_path = manager
+        .db_path()
+        .ok_or_else(|| DatabaseError::Query("In-memory databases are not backed up".to_string()))?;
+    let dir = schedule.dir(db_path);
+    let existing = list_backups(&dir)?;
+    let now = Utc::now().timestamp();
+    let due = |kind: BackupKind, period: i64| {
+        existing
+            .iter()
+            .find(|backup| backup.kind == kind)
+            .is_none_or(|latest| now - latest.created_at >= period)
+    };
+
+    let mut created = Vec::new();
+    if schedule.daily > 0 && due(BackupKind::Daily, DAY_SECS) {
+        created.push(create_backup(manager, &dir, BackupKind::Daily).await?);
+    }
+    if schedule.weekly > 0 && due(BackupKind::Weekly, WEEK_SECS) {
+        created.push(create_backup(manager, &dir, BackupKind::Weekly).await?);
+    }
+
+    rotate_backups(&dir, schedule)?;
+    Ok(created)
+}
+
+/// Delete daily and weekly snapshots beyond the retention counts, returning the removed files
+pub fn rotate_backups(dir: &Path, schedule: &BackupSchedule) -> Result<Vec<PathBuf>> {
+    let backups = list_backups(dir)?;
+    let mut removed = Vec::new();
+
+    for (kind, keep) in [(BackupKind::Daily, schedule.daily), (BackupKind::Weekly, schedule.weekly)] {
+        for backup in backups.iter().filter(|backup| backup.kind == kind).skip(keep) {
+            fs::remove_file(&backup.path)?;
+            removed.push(backup.path.clone());
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Outcome of re-keying the backups in a directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RekeyReport {
+    pub rekeyed: usize,
+    pub skipped: Vec<PathBuf>, // Encrypted under a passphrase other than the current one
+}
+
+/// Put every backup in `dir` under the passphrase `new`
+///
+/// Plaintext backups are encrypted and those under `current` are re-keyed.
+/// Backups under any other passphrase are left as they are and reported, since
+/// they can still be restored with their own passphrase.
+pub async fn rekey_backups(dir: &Path, current: Option<&str>, new: &str) -> Result<RekeyReport> {
+    let mut report = RekeyReport::default();
+    for backup in list_backups(dir)? {
+        let rekeyed = if !encryption::is_encrypted(&backup.path)? {
+            encryption::encrypt_database(&backup.path, new).await
+        } else if let Some(current) = current {
+            encryption::change_passphrase(&backup.path, current, new).await
+        } else {
+            Err(DatabaseError::Locked)
+        };
+        match rekeyed {
+            Ok(()) => report.rekeyed += 1,
+            Err(DatabaseError::Locked | DatabaseError::WrongPassphrase) => report.skipped.push(backup.path),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(report)
+}
+
+/// Check a backup before it is restored; encrypted backups need their passphrase
+pub async fn verify_backup(path: &Path, passphrase: Option<&str>) -> Result<()> {
+    SqliteProvider::verify_file(path, passphrase).await
+}
+
+/// Replace the database file at `db_path` with a verified backup
+///
+/// The database must be closed. `backup_passphrase` opens the backup; when the
+/// database is encrypted the restored copy is re-keyed to its `passphrase`, so
+/// a backup taken under an older passphrase comes back under the current one.
+/// An encrypted backup restored over a plaintext database stays encrypted. Stale WAL and shared-memory files are removed so SQLite doesn't replay them
+/// over the restored data.
+pub async fn restore_backup(
+    backup: &Path,
+    db_path: &Path,
+    backup_passphrase: Option<&str>,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    verify_backup(backup, backup_passphrase).await?;
+
+    // Copy next to the target first so the swap itself is a rename
+    let staged = db_path.with_extension("restoring");
+    fs::copy(backup, &staged)?;
+    let rekeyed = match (backup_passphrase, passphrase) {
+        (Some(old), Some(new)) if old != new => encryption::change_passphrase(&staged, old, new).await,
+        (None, Some(new)) => encryption::encrypt_database(&staged, new).await,
+        _ => Ok(()),
+    };
+    if let Err(e) = rekeyed {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+
+    encryption::remove_sidecars(db_path)?;
+    fs::rename(&staged, db_path).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        DatabaseError::Io(e)
+    })?;
+
+    Ok(())
+}
+
+/// Check hourly whether scheduled snapshots are due, for as long as the app runs
+///
+/// The schedule is re-read from the settings on every tick, so enabling it or
+/// changing retention takes effect without a restart.
+pub fn spawn_backup_scheduler(state: DatabaseState) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let schedule = BackupSchedule::from_settings();
+            if schedule.enabled {
+                let manager = state.read().await.clone();
+                if let Some(manager) = manager.filter(|manager| manager.db_path().is_some()) {
+                    if let Err(e) = run_scheduled_backups(&manager, &schedule).await {
+                        eprintln!("[Backup] Scheduled backup failed: {}", e);
+                    }
+                }
+            }
+            tokio::time::sleep(SCHEDULE_INTERVAL).await;
+        }
+    });
+}
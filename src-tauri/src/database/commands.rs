@@ -3,26 +3,142 @@
 /// This module exposes the new session/message operations to the frontend.
 /// These commands provide a clean API for interacting with the new two-table design.
 
-use crate::database::{models::*, DatabaseConfig, DatabaseManager, DatabaseProvider};
+use crate::database::backup::{self, BackupInfo, BackupKind};
+use crate::database::export::{self, ExportFormat};
+use crate::database::importers::{self, ImportReport, ImportSource};
+use crate::database::{encryption, models::*, persona_files, DatabaseConfig, DatabaseError, DatabaseManager, DatabaseProvider, PoolOptions};
 use crate::connectors::Connector;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tauri::State;
-use tokio::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::RwLock;
 
 /// Application state containing the database manager
-pub type DatabaseState = Arc<Mutex<Option<DatabaseManager>>>;
+///
+/// Commands clone the manager out of the read lock and query without holding it;
+/// the manager shares one connection pool between clones. Only opening, closing
+/// or replacing the database takes the write lock.
+pub type DatabaseState = Arc<RwLock<Option<DatabaseManager>>>;
+
+/// Clone the open database handle, holding the lock only for the clone
+pub async fn database(state: &DatabaseState) -> Result<DatabaseManager, String> {
+    state
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "Database not initialized".to_string())
+}
+
+/// Event emitted with a `DatabaseStatus` whenever the database is opened or fails to open
+pub const DATABASE_STATUS_EVENT: &str = "database-status";
+
+/// Event emitted with an `ImportProgress` after each imported conversation
+pub const IMPORT_PROGRESS_EVENT: &str = "import-progress";
+
+/// How long trashed items are kept when `purge_trash` is called without an age
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Error for commands whose failures the frontend must tell apart, sent as `{ code, message }`
+///
+/// `code` is `locked` until the passphrase is given, `wrong_passphrase` when it
+/// doesn't match, and `database_error` for everything else.
+#[derive(Debug, Serialize)]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl CommandError {
+    fn new(context: &str, error: DatabaseError) -> Self {
+        let code = match error {
+            DatabaseError::Locked => "locked",
+            DatabaseError::WrongPassphrase => "wrong_passphrase",
+            _ => "database_error",
+        };
+        Self {
+            code,
+            message: format!("{}: {}", context, error),
+        }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_string())
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self {
+            code: "database_error",
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseReadiness {
+    Ready,
+    Locked, // Encrypted and waiting for `unlock_database`
+    Error,
+    Uninitialized,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStatus {
+    pub state: DatabaseReadiness,
+    pub path: Option<String>,
+    pub message: Option<String>,
+}
+
+impl DatabaseStatus {
+    fn ready(path: String) -> Self {
+        Self {
+            state: DatabaseReadiness::Ready,
+            path: Some(path),
+            message: None,
+        }
+    }
+}
 
 // Parameter structs for Tauri commands
 #[derive(Deserialize)]
 pub struct SaveMessageParams {
     #[serde(rename = "sessionId")]
     pub session_id: i64,
-    pub role: String,
-    pub content: String,
+    pub role: MessageRole,
+    #[serde(default)]
+    pub content: String, // Derived from contentParts when those are given
+    #[serde(rename = "contentParts")]
+    pub content_parts: Option<Vec<ContentPart>>,
     pub embedding: Option<Vec<u8>>,
     #[serde(rename = "recallScore")]
     pub recall_score: Option<f64>,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<i64>,
+    #[serde(rename = "tokenCount")]
+    pub token_count: Option<i64>,
+    #[serde(rename = "reasoningTokens")]
+    pub reasoning_tokens: Option<i64>,
+    #[serde(rename = "generationParams")]
+    pub generation_params: Option<GenerationParams>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMessageParams {
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+    pub content: Option<String>,
+    #[serde(rename = "contentParts")]
+    pub content_parts: Option<Vec<ContentPart>>,
+    #[serde(rename = "recallScore", default, deserialize_with = "double_option")]
+    pub recall_score: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub embedding: Option<Option<Vec<u8>>>,
+    #[serde(rename = "changedBy")]
+    pub changed_by: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -32,57 +148,470 @@ pub struct GetMessagesParams {
     pub limit: Option<i64>,
 }
 
+#[derive(Deserialize)]
+pub struct GetMessagesPageParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListBranchesParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct SwitchBranchParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+}
+
 #[derive(Deserialize)]
 pub struct GetSessionParams {
     #[serde(rename = "sessionId")]
     pub session_id: i64,
 }
+
+#[derive(Deserialize)]
+pub struct ForkSessionParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    #[serde(rename = "messageId")]
+    pub message_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSessionParams {
+    #[serde(rename = "sessionId")]
+    pub session_id: i64,
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub role: Option<Option<String>>, // null clears the field
+    #[serde(default, deserialize_with = "double_option")]
+    pub goals: Option<Option<String>>,
+    #[serde(rename = "llmProvider", default, deserialize_with = "double_option")]
+    pub llm_provider: Option<Option<String>>,
+    #[serde(rename = "modelId", default, deserialize_with = "double_option")]
+    pub model_id: Option<Option<String>>,
+    pub status: Option<SessionStatus>,
+    #[serde(rename = "includeReasoning")]
+    pub include_reasoning: Option<bool>,
+    #[serde(rename = "generationParams")]
+    pub generation_params: Option<GenerationParams>,
+}
+#[derive(Deserialize)]
+pub struct UpdatePersonaParams {
+    #[serde(rename = "personaId")]
+    pub persona_id: i64,
+    pub name: Option<String>,
+    #[serde(rename = "systemPrompt", default, deserialize_with = "double_option")]
+    pub system_prompt: Option<Option<String>>, // null clears the field
+    #[serde(default, deserialize_with = "double_option")]
+    pub goals: Option<Option<String>>,
+    #[serde(rename = "llmProvider", default, deserialize_with = "double_option")]
+    pub llm_provider: Option<Option<String>>,
+    #[serde(rename = "modelId", default, deserialize_with = "double_option")]
+    pub model_id: Option<Option<String>>,
+    #[serde(rename = "generationParams")]
+    pub generation_params: Option<GenerationParams>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub avatar: Option<Option<String>>,
+    #[serde(rename = "applyToSessions", default)]
+    pub apply_to_sessions: bool, // Copy the changed fields to sessions using the persona
+}
+
+/// Open the backend configured in `memory_config` and announce the outcome
+///
+/// Runs during app setup and again when `save_settings` changes the database
+/// settings. A database that can't be opened is closed rather than left pointing
+/// at the previous configuration.
+pub async fn init_from_settings(app: &AppHandle, state: &DatabaseState) -> DatabaseStatus {
+    let mut state_guard = state.write().await;
+    open_from_settings(app, &mut state_guard, None).await
+}
+
+/// `init_from_settings` for a caller already holding the write lock
+///
+/// Without a `passphrase`, the open database's own is reused when its path is reopened.
+pub async fn open_from_settings(
+    app: &AppHandle,
+    slot: &mut Option<DatabaseManager>,
+    passphrase: Option<String>,
+) -> DatabaseStatus {
+    let status = match DatabaseConfig::from_settings() {
+        Ok(config) => {
+            let db_path = config.connection_string;
+            let passphrase = passphrase.or_else(|| reusable_passphrase(slot, &db_path));
+
+            match open_database(slot, db_path.clone(), passphrase).await {
+                Ok(_) => DatabaseStatus::ready(db_path),
+                Err(e) => {
+                    *slot = None;
+                    DatabaseStatus {
+                        state: if e.code == "locked" { DatabaseReadiness::Locked } else { DatabaseReadiness::Error },
+                        path: Some(db_path),
+                        message: Some(e.message),
+                    }
+                }
+            }
+        }
+        Err(e) => DatabaseStatus {
+            state: DatabaseReadiness::Error,
+            path: None,
+            message: Some(e.to_string()),
+        },
+    };
+
+    let _ = app.emit(DATABASE_STATUS_EVENT, &status);
+    status
+}
+
+/// Current database state, for a frontend that missed the startup event
+#[tauri::command]
+pub async fn get_database_status(state: State<'_, DatabaseState>) -> Result<DatabaseStatus, String> {
+    if let Some(manager) = state.read().await.as_ref() {
+        let path = manager
+            .db_path()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| ":memory:".to_string());
+        return Ok(DatabaseStatus::ready(path));
+    }
+
+    let db_path = configured_db_path();
+    let locked = encryption::is_encrypted(std::path::Path::new(&db_path)).unwrap_or(false);
+    Ok(DatabaseStatus {
+        state: if locked { DatabaseReadiness::Locked } else { DatabaseReadiness::Uninitialized },
+        path: Some(db_path),
+        message: None,
+    })
+}
+
+/// Open the database; fails with code `locked` while an encrypted database has not been unlocked
+///
+/// Without a path, the one configured in `memory_config` is used.
 #[tauri::command]
 pub async fn init_database(
+    app: AppHandle,
     database_path: Option<String>,
     state: State<'_, DatabaseState>,
-) -> Result<String, String> {
-    let db_path = database_path.unwrap_or_else(|| {
-        DatabaseManager::default_db_path()
-            .to_string_lossy()
-            .to_string()
-    });
+) -> Result<String, CommandError> {
+    let db_path = database_path.unwrap_or_else(configured_db_path);
+
+    // Re-initialising an unlocked database keeps it unlocked
+    let mut state_guard = state.write().await;
+    let passphrase = reusable_passphrase(&state_guard, &db_path);
+
+    let message = open_database(&mut state_guard, db_path.clone(), passphrase).await?;
+    let _ = app.emit(DATABASE_STATUS_EVENT, DatabaseStatus::ready(db_path));
+    Ok(message)
+}
+
+/// Open an encrypted database with its passphrase
+#[tauri::command]
+pub async fn unlock_database(
+    app: AppHandle,
+    passphrase: String,
+    database_path: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<String, CommandError> {
+    let db_path = database_path.unwrap_or_else(configured_db_path);
+
+    let mut state_guard = state.write().await;
+    let message = open_database(&mut state_guard, db_path.clone(), Some(passphrase)).await?;
+    let _ = app.emit(DATABASE_STATUS_EVENT, DatabaseStatus::ready(db_path));
+    Ok(message)
+}
+
+/// Database file configured in `memory_config`, or the default one
+fn configured_db_path() -> String {
+    DatabaseConfig::from_settings()
+        .map(|config| config.connection_string)
+        .unwrap_or_else(|_| DatabaseManager::default_db_path().to_string_lossy().to_string())
+}
+
+/// Passphrase of the open database when `db_path` is reopened, so it stays unlocked
+fn reusable_passphrase(slot: &Option<DatabaseManager>, db_path: &str) -> Option<String> {
+    slot.as_ref()
+        .filter(|manager| manager.db_path() == Some(std::path::Path::new(db_path)))
+        .and_then(|manager| manager.passphrase().map(str::to_string))
+}
+
+/// Encrypt the open plaintext database in place and keep it unlocked
+///
+/// Existing backups are encrypted with the same passphrase, so no plaintext
+/// copy is left behind.
+#[tauri::command]
+pub async fn encrypt_database(
+    passphrase: String,
+    state: State<'_, DatabaseState>,
+) -> Result<String, CommandError> {
+    let mut state_guard = state.write().await;
+    let manager = state_guard
+        .take()
+        .ok_or("Database not initialized")?;
+    let Some(db_path) = manager.db_path().map(|path| path.to_string_lossy().to_string()) else {
+        *state_guard = Some(manager);
+        return Err("An in-memory database cannot be encrypted".into());
+    };
+    if manager.passphrase().is_some() {
+        *state_guard = Some(manager);
+        return Err("Database is already encrypted".into());
+    }
+
+    manager.close().await;
+    if let Err(e) = encryption::encrypt_database(std::path::Path::new(&db_path), &passphrase).await {
+        // Leave the plaintext database open again if encryption failed
+        let _ = open_database(&mut state_guard, db_path, None).await;
+        return Err(CommandError::new("Failed to encrypt database", e));
+    }
 
+    let opened = open_database(&mut state_guard, db_path.clone(), Some(passphrase.clone())).await?;
+    Ok(format!("{}{}", opened, rekey_backups(&db_path, None, &passphrase).await))
+}
+
+/// Re-encrypt the open database under a new passphrase
+///
+/// Backups under the current passphrase are re-keyed too, so they can still be
+/// restored with the new one.
+#[tauri::command]
+pub async fn change_database_passphrase(
+    current_passphrase: String,
+    new_passphrase: String,
+    state: State<'_, DatabaseState>,
+) -> Result<String, CommandError> {
+    let mut state_guard = state.write().await;
+    let manager = state_guard
+        .take()
+        .ok_or("Database not initialized")?;
+    let Some(db_path) = manager.db_path().map(|path| path.to_string_lossy().to_string()) else {
+        *state_guard = Some(manager);
+        return Err("An in-memory database has no passphrase".into());
+    };
+    if manager.passphrase().is_none() {
+        *state_guard = Some(manager);
+        return Err("Database is not encrypted".into());
+    }
+    if manager.passphrase() != Some(current_passphrase.as_str()) {
+        *state_guard = Some(manager);
+        return Err(CommandError::new("Failed to change passphrase", DatabaseError::WrongPassphrase));
+    }
+
+    manager.close().await;
+    if let Err(e) = encryption::change_passphrase(std::path::Path::new(&db_path), &current_passphrase, &new_passphrase).await {
+        let _ = open_database(&mut state_guard, db_path, Some(current_passphrase)).await;
+        return Err(CommandError::new("Failed to change passphrase", e));
+    }
+
+    let opened = open_database(&mut state_guard, db_path.clone(), Some(new_passphrase.clone())).await?;
+    Ok(format!(
+        "{}{}",
+        opened,
+        rekey_backups(&db_path, Some(&current_passphrase), &new_passphrase).await
+    ))
+}
+
+/// Put the backups of `db_path` under the new passphrase, describing the outcome for the user
+///
+/// The database itself is already re-keyed by then, so problems are reported
+/// rather than failing the command.
+async fn rekey_backups(db_path: &str, current: Option<&str>, new: &str) -> String {
+    let db_path = std::path::Path::new(db_path);
+    let mut dirs = vec![backup::backups_dir_for(db_path)];
+    let scheduled = backup::BackupSchedule::from_settings().dir(db_path);
+    if !dirs.contains(&scheduled) {
+        dirs.push(scheduled);
+    }
+
+    let mut rekeyed = 0;
+    let mut notes = Vec::new();
+    for dir in dirs {
+        match backup::rekey_backups(&dir, current, new).await {
+            Ok(report) => {
+                rekeyed += report.rekeyed;
+                notes.extend(report.skipped.iter().map(|path| {
+                    format!("{} still needs the passphrase it was taken with", path.display())
+                }));
+            }
+            Err(e) => notes.push(format!("backups in {} could not be re-encrypted: {}", dir.display(), e)),
+        }
+    }
+
+    let mut message = format!("; {} backups re-encrypted", rekeyed);
+    for note in notes {
+        message.push_str("; ");
+        message.push_str(&note);
+    }
+    message
+}
+
+/// Open, migrate and install the database at `db_path` in the locked state slot
+async fn open_database(
+    slot: &mut Option<DatabaseManager>,
+    db_path: String,
+    passphrase: Option<String>,
+) -> Result<String, CommandError> {
     let config = DatabaseConfig {
         provider: DatabaseProvider::SQLite,
         connection_string: db_path.clone(),
+        passphrase,
+        pool: PoolOptions::from_settings(),
     };
 
     let manager = DatabaseManager::new(config)
         .await
-        .map_err(|e| format!("Failed to initialize database: {}", e))?;
+        .map_err(|e| CommandError::new("Failed to initialize database", e))?;
+
+    let legacy_report = manager
+        .migrate()
+        .await
+        .map_err(|e| CommandError::new("Failed to run migrations", e))?;
+
+    *slot = Some(manager);
+
+    match legacy_report {
+        Some(report) if !report.unmapped_fields.is_empty() => Ok(format!(
+            "Database initialized at: {} (imported {} legacy rows; expiry or metadata of {} could not be kept)",
+            db_path,
+            report.total_rows(),
+            report.unmapped_fields.len()
+        )),
+        Some(report) => Ok(format!(
+            "Database initialized at: {} (imported {} legacy rows)",
+            db_path,
+            report.total_rows()
+        )),
+        None => Ok(format!("Database initialized at: {}", db_path)),
+    }
+}
+
+// === Backup Commands ===
+
+/// Snapshot the running database; defaults to `backups/` next to the database file
+#[tauri::command]
+pub async fn create_backup(
+    directory: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<BackupInfo, String> {
+    let manager = database(&state).await?;
+
+    let dir = backup_dir(&manager, directory)?;
+    backup::create_backup(&manager, &dir, BackupKind::Manual)
+        .await
+        .map_err(|e| format!("Failed to create backup: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_backups(
+    directory: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<BackupInfo>, String> {
+    let manager = database(&state).await?;
+
+    let dir = backup_dir(&manager, directory)?;
+    backup::list_backups(&dir).map_err(|e| format!("Failed to list backups: {}", e))
+}
+
+/// Swap a backup in for the current database after checking its integrity
+///
+/// The current database is snapshotted first so a restore can itself be undone.
+/// `backup_passphrase` opens a backup taken under an older passphrase and
+/// defaults to the current one; the restored database keeps the current
+/// passphrase when there is one.
+#[tauri::command]
+pub async fn restore_backup(
+    path: String,
+    backup_passphrase: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<String, String> {
+    let backup_path = std::path::PathBuf::from(&path);
+    let mut state_guard = state.write().await;
+    let manager = state_guard
+        .as_ref()
+        .ok_or("Database not initialized")?;
+    let db_path = manager
+        .db_path()
+        .ok_or("An in-memory database cannot be restored")?
+        .to_path_buf();
+
+    let passphrase = manager.passphrase().map(str::to_string);
+    let backup_passphrase = backup_passphrase.or_else(|| passphrase.clone());
+    backup::verify_backup(&backup_path, backup_passphrase.as_deref())
+        .await
+        .map_err(|e| format!("Backup failed verification: {}", e))?;
+    let previous = backup::create_backup(manager, &backup::backups_dir_for(&db_path), BackupKind::PreRestore)
+        .await
+        .map_err(|e| format!("Failed to back up the current database: {}", e))?;
+
+    manager.close().await;
+    *state_guard = None;
+    let restored = backup::restore_backup(
+        &backup_path,
+        &db_path,
+        backup_passphrase.as_deref(),
+        passphrase.as_deref(),
+    )
+    .await;
 
+    // Reopen whatever is now on disk, so a failed swap leaves the old database usable
+    let passphrase = match &restored {
+        Ok(()) => passphrase.or(backup_passphrase),
+        Err(_) => passphrase,
+    };
+    let manager = DatabaseManager::new(DatabaseConfig {
+        provider: DatabaseProvider::SQLite,
+        connection_string: db_path.to_string_lossy().to_string(),
+        passphrase,
+        pool: PoolOptions::from_settings(),
+    })
+    .await
+    .map_err(|e| format!("Failed to reopen database: {}", e))?;
     manager
         .migrate()
         .await
         .map_err(|e| format!("Failed to run migrations: {}", e))?;
-
-    let mut state_guard = state.lock().await;
     *state_guard = Some(manager);
 
-    Ok(format!("Database initialized at: {}", db_path))
+    restored.map_err(|e| format!("Failed to restore backup: {}", e))?;
+    Ok(format!(
+        "Restored {}; the previous database was saved to {}",
+        path,
+        previous.path.display()
+    ))
+}
+
+fn backup_dir(manager: &DatabaseManager, directory: Option<String>) -> Result<std::path::PathBuf, String> {
+    match directory {
+        Some(directory) => Ok(std::path::PathBuf::from(directory)),
+        None => manager
+            .db_path()
+            .map(backup::backups_dir_for)
+            .ok_or_else(|| "An in-memory database has no backup directory".to_string()),
+    }
 }
 
 /// Get the current database path
 #[tauri::command]
-pub async fn get_database_path() -> Result<String, String> {
-    Ok(DatabaseManager::default_db_path()
-        .to_string_lossy()
-        .to_string())
+pub async fn get_database_path(state: State<'_, DatabaseState>) -> Result<String, String> {
+    let open_path = state
+        .read()
+        .await
+        .as_ref()
+        .and_then(|manager| manager.db_path().map(|path| path.to_string_lossy().to_string()));
+
+    Ok(open_path.unwrap_or_else(configured_db_path))
 }
 
 /// Get database statistics
 #[tauri::command]
 pub async fn get_database_stats(state: State<'_, DatabaseState>) -> Result<DatabaseStats, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
@@ -91,13 +620,57 @@ pub async fn get_database_stats(state: State<'_, DatabaseState>) -> Result<Datab
         .map_err(|e| format!("Failed to get database stats: {}", e))
 }
 
-/// Clear all memory data
+// === Maintenance Commands ===
+
+#[tauri::command]
+pub async fn check_database_integrity(state: State<'_, DatabaseState>) -> Result<IntegrityReport, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .integrity_check()
+        .await
+        .map_err(|e| format!("Failed to check database integrity: {}", e))
+}
+
+/// Shrink the database file after sessions have been purged
+#[tauri::command]
+pub async fn vacuum_database(state: State<'_, DatabaseState>) -> Result<VacuumReport, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .vacuum()
+        .await
+        .map_err(|e| format!("Failed to vacuum database: {}", e))
+}
+
+#[tauri::command]
+pub async fn analyze_database(state: State<'_, DatabaseState>) -> Result<AnalyzeReport, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .analyze()
+        .await
+        .map_err(|e| format!("Failed to analyze database: {}", e))
+}
+
+/// Checkpoint the WAL; defaults to `truncate`, which also shrinks the WAL file
+#[tauri::command]
+pub async fn checkpoint_database(
+    mode: Option<CheckpointMode>,
+    state: State<'_, DatabaseState>,
+) -> Result<CheckpointReport, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .checkpoint_wal(mode.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to checkpoint database: {}", e))
+}
+
+/// Move all sessions to the trash
 #[tauri::command]
 pub async fn clear_all_memory(state: State<'_, DatabaseState>) -> Result<String, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
@@ -105,7 +678,7 @@ pub async fn clear_all_memory(state: State<'_, DatabaseState>) -> Result<String,
         .await
         .map_err(|e| format!("Failed to clear memory: {}", e))?;
 
-    Ok("All memory data cleared successfully".to_string())
+    Ok("All memory data moved to trash".to_string())
 }
 
 // === Session Commands ===
@@ -117,12 +690,10 @@ pub async fn create_session(
     goals: Option<String>,
     llm_provider: Option<String>,
     model_id: Option<String>,
+    persona_id: Option<i64>,
     state: State<'_, DatabaseState>,
 ) -> Result<Session, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     let create_session = CreateSession { 
         name, 
@@ -130,7 +701,8 @@ pub async fn create_session(
         goals, 
         llm_provider, 
         model_id, 
-        status: None 
+        status: None,
+        persona_id,
     };
 
     manager
@@ -140,16 +712,19 @@ pub async fn create_session(
         .map_err(|e| format!("Failed to create session: {}", e))
 }
 
+/// List sessions with their activity summary, optionally filtered and sorted
+///
+/// Archived sessions are hidden by default and the newest activity comes first.
 #[tauri::command]
-pub async fn get_sessions(state: State<'_, DatabaseState>) -> Result<Vec<Session>, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+pub async fn get_sessions(
+    query: Option<SessionQuery>,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<SessionSummary>, String> {
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
-        .get_sessions()
+        .session_summaries(query.unwrap_or_default())
         .await
         .map_err(|e| format!("Failed to get sessions: {}", e))
 }
@@ -159,10 +734,7 @@ pub async fn get_session_by_id(
     params: GetSessionParams,
     state: State<'_, DatabaseState>,
 ) -> Result<Session, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
@@ -171,6 +743,46 @@ pub async fn get_session_by_id(
         .map_err(|e| format!("Failed to get session: {}", e))
 }
 
+#[tauri::command]
+pub async fn update_session(
+    params: UpdateSessionParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    let update = UpdateSession {
+        name: params.name,
+        role: params.role,
+        goals: params.goals,
+        llm_provider: params.llm_provider,
+        model_id: params.model_id,
+        status: params.status,
+        include_reasoning: params.include_reasoning,
+        generation_params: params.generation_params,
+    };
+
+    manager
+        .memory_repo()
+        .update_session(params.session_id, update)
+        .await
+        .map_err(|e| format!("Failed to update session: {}", e))
+}
+
+/// Start a new session from the transcript ending at the given message
+#[tauri::command]
+pub async fn fork_session(
+    params: ForkSessionParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .fork_session(params.session_id, params.message_id)
+        .await
+        .map_err(|e| format!("Failed to fork session: {}", e))
+}
+
 #[tauri::command]
 pub async fn delete_session(
     session_id: i64,
@@ -178,10 +790,7 @@ pub async fn delete_session(
 ) -> Result<bool, String> {
     println!("delete_session called with session_id: {}", session_id);
     
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     println!("Database manager found, calling delete_session...");
     
@@ -199,6 +808,320 @@ pub async fn delete_session(
     result
 }
 
+// === Session Organisation Commands ===
+
+#[tauri::command]
+pub async fn create_folder(
+    name: String,
+    state: State<'_, DatabaseState>,
+) -> Result<Folder, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .create_folder(name)
+        .await
+        .map_err(|e| format!("Failed to create folder: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_folders(state: State<'_, DatabaseState>) -> Result<Vec<Folder>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_folders()
+        .await
+        .map_err(|e| format!("Failed to list folders: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_folder(
+    folder_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<bool, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .delete_folder(folder_id)
+        .await
+        .map_err(|e| format!("Failed to delete folder: {}", e))
+}
+
+/// Move a session into a folder, or out of any folder when `folder_id` is null
+#[tauri::command]
+pub async fn set_session_folder(
+    session_id: i64,
+    folder_id: Option<i64>,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .set_session_folder(session_id, folder_id)
+        .await
+        .map_err(|e| format!("Failed to set session folder: {}", e))
+}
+
+/// Replace the tags on a session
+#[tauri::command]
+pub async fn set_session_tags(
+    session_id: i64,
+    tags: Vec<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .set_session_tags(session_id, tags)
+        .await
+        .map_err(|e| format!("Failed to set session tags: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_tags(state: State<'_, DatabaseState>) -> Result<Vec<String>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_tags()
+        .await
+        .map_err(|e| format!("Failed to list tags: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_session_pinned(
+    session_id: i64,
+    pinned: bool,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .set_session_pinned(session_id, pinned)
+        .await
+        .map_err(|e| format!("Failed to set session pinned: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_session_archived(
+    session_id: i64,
+    archived: bool,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    let status = if archived { SessionStatus::Archived } else { SessionStatus::Open };
+
+    manager
+        .memory_repo()
+        .set_session_status(session_id, status)
+        .await
+        .map_err(|e| format!("Failed to set session archived: {}", e))
+}
+
+/// Close a session so its messages become read-only
+#[tauri::command]
+pub async fn close_session(
+    session_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .set_session_status(session_id, SessionStatus::Closed)
+        .await
+        .map_err(|e| format!("Failed to close session: {}", e))
+}
+
+#[tauri::command]
+pub async fn reopen_session(
+    session_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .set_session_status(session_id, SessionStatus::Open)
+        .await
+        .map_err(|e| format!("Failed to reopen session: {}", e))
+}
+
+// === Export Commands ===
+
+/// Render one session as Markdown, lossless JSON or self-contained HTML
+#[tauri::command]
+pub async fn export_session(
+    session_id: i64,
+    format: ExportFormat,
+    state: State<'_, DatabaseState>,
+) -> Result<String, String> {
+    let manager = database(&state).await?;
+
+    export::export_session(manager.memory_repo(), session_id, format)
+        .await
+        .map_err(|e| format!("Failed to export session: {}", e))
+}
+
+/// Write sessions to a directory, one file each; every session when no ids are given
+#[tauri::command]
+pub async fn export_sessions(
+    format: ExportFormat,
+    directory: String,
+    session_ids: Option<Vec<i64>>,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<String>, String> {
+    let manager = database(&state).await?;
+
+    let written = export::export_sessions(manager.memory_repo(), session_ids, format, std::path::Path::new(&directory))
+        .await
+        .map_err(|e| format!("Failed to export sessions: {}", e))?;
+
+    Ok(written.iter().map(|path| path.display().to_string()).collect())
+}
+
+// === Import Commands ===
+
+/// Import a ChatGPT or Claude `conversations.json`; conversations imported before are skipped
+#[tauri::command]
+pub async fn import_conversations(
+    app: AppHandle,
+    source: ImportSource,
+    path: String,
+    state: State<'_, DatabaseState>,
+) -> Result<ImportReport, String> {
+    let manager = database(&state).await?;
+
+    importers::import_conversations(manager.memory_repo(), source, std::path::Path::new(&path), |progress| {
+        let _ = app.emit(IMPORT_PROGRESS_EVENT, progress);
+    })
+    .await
+    .map_err(|e| format!("Failed to import conversations: {}", e))
+}
+
+// === Persona Commands ===
+
+#[tauri::command]
+pub async fn create_persona(
+    persona: CreatePersona,
+    state: State<'_, DatabaseState>,
+) -> Result<Persona, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .create_persona(persona)
+        .await
+        .map_err(|e| format!("Failed to create persona: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_personas(state: State<'_, DatabaseState>) -> Result<Vec<Persona>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_personas()
+        .await
+        .map_err(|e| format!("Failed to list personas: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_persona(
+    params: UpdatePersonaParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Persona, String> {
+    let manager = database(&state).await?;
+
+    let update = UpdatePersona {
+        name: params.name,
+        system_prompt: params.system_prompt,
+        goals: params.goals,
+        llm_provider: params.llm_provider,
+        model_id: params.model_id,
+        generation_params: params.generation_params,
+        avatar: params.avatar,
+    };
+
+    manager
+        .memory_repo()
+        .update_persona(params.persona_id, update, params.apply_to_sessions)
+        .await
+        .map_err(|e| format!("Failed to update persona: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_persona(
+    persona_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<bool, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .delete_persona(persona_id)
+        .await
+        .map_err(|e| format!("Failed to delete persona: {}", e))
+}
+
+/// Import every YAML persona in the directory (default `personas/` in the active workspace)
+///
+/// Personas are matched by name, so an existing persona is updated in place.
+#[tauri::command]
+pub async fn import_personas(
+    directory: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<Persona>, String> {
+    let manager = database(&state).await?;
+
+    let dir = directory
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(persona_files::default_personas_dir);
+    let personas = persona_files::read_personas(&dir)
+        .map_err(|e| format!("Failed to read personas: {}", e))?;
+
+    let mut imported = Vec::with_capacity(personas.len());
+    for persona in personas {
+        let saved = manager
+            .memory_repo()
+            .upsert_persona(persona)
+            .await
+            .map_err(|e| format!("Failed to import persona: {}", e))?;
+        imported.push(saved);
+    }
+
+    Ok(imported)
+}
+
+/// Write every persona to the directory (default `personas/` in the active workspace), one YAML file each
+#[tauri::command]
+pub async fn export_personas(
+    directory: Option<String>,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<String>, String> {
+    let manager = database(&state).await?;
+
+    let personas = manager
+        .memory_repo()
+        .list_personas()
+        .await
+        .map_err(|e| format!("Failed to list personas: {}", e))?;
+
+    let dir = directory
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(persona_files::default_personas_dir);
+    let written = persona_files::export_personas(personas, &dir)
+        .map_err(|e| format!("Failed to export personas: {}", e))?;
+
+    Ok(written.iter().map(|path| path.display().to_string()).collect())
+}
+
 // === Message Commands ===
 
 #[tauri::command]
@@ -206,10 +1129,7 @@ pub async fn save_message(
     params: SaveMessageParams,
     state: State<'_, DatabaseState>,
 ) -> Result<Message, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     let create_message = CreateMessage {
         session_id: params.session_id,
@@ -217,6 +1137,11 @@ pub async fn save_message(
         content: params.content,
         embedding: params.embedding,
         recall_score: params.recall_score,
+        parent_id: params.parent_id,
+        token_count: params.token_count,
+        content_parts: params.content_parts,
+        reasoning_tokens: params.reasoning_tokens,
+        generation_params: params.generation_params,
     };
 
     manager
@@ -231,10 +1156,7 @@ pub async fn get_recent_messages(
     params: GetMessagesParams,
     state: State<'_, DatabaseState>,
 ) -> Result<Vec<Message>, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
@@ -243,15 +1165,54 @@ pub async fn get_recent_messages(
         .map_err(|e| format!("Failed to get recent messages: {}", e))
 }
 
+#[tauri::command]
+pub async fn get_messages_page(
+    params: GetMessagesPageParams,
+    state: State<'_, DatabaseState>,
+) -> Result<MessagePage, String> {
+    let manager = database(&state).await?;
+
+    let cursor = MessageCursor {
+        before: params.before,
+        after: params.after,
+        limit: params.limit,
+    };
+
+    manager
+        .memory_repo()
+        .messages_page(params.session_id, cursor)
+        .await
+        .map_err(|e| format!("Failed to get messages page: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_message(
+    params: UpdateMessageParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Message, String> {
+    let manager = database(&state).await?;
+
+    let update = UpdateMessage {
+        content: params.content,
+        content_parts: params.content_parts,
+        recall_score: params.recall_score,
+        embedding: params.embedding,
+        changed_by: params.changed_by,
+    };
+
+    manager
+        .memory_repo()
+        .update_message(params.message_id, update)
+        .await
+        .map_err(|e| format!("Failed to update message: {}", e))
+}
+
 #[tauri::command]
 pub async fn delete_message(
     message_id: i64,
     state: State<'_, DatabaseState>,
 ) -> Result<bool, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
@@ -260,6 +1221,142 @@ pub async fn delete_message(
         .map_err(|e| format!("Failed to delete message: {}", e))
 }
 
+#[tauri::command]
+pub async fn list_message_revisions(
+    message_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<MessageRevision>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_message_revisions(message_id)
+        .await
+        .map_err(|e| format!("Failed to list message revisions: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_message_revision(
+    revision_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Message, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .restore_message_revision(revision_id)
+        .await
+        .map_err(|e| format!("Failed to restore message revision: {}", e))
+}
+
+// === Trash Commands ===
+
+#[tauri::command]
+pub async fn restore_session(
+    session_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .restore_session(session_id)
+        .await
+        .map_err(|e| format!("Failed to restore session: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_message(
+    message_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Message, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .restore_message(message_id)
+        .await
+        .map_err(|e| format!("Failed to restore message: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_trash(state: State<'_, DatabaseState>) -> Result<TrashContents, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_trash()
+        .await
+        .map_err(|e| format!("Failed to list trash: {}", e))
+}
+
+/// Permanently remove items that have been in the trash longer than the given age
+#[tauri::command]
+pub async fn purge_trash(
+    older_than_days: Option<i64>,
+    state: State<'_, DatabaseState>,
+) -> Result<PurgeReport, String> {
+    let manager = database(&state).await?;
+
+    let older_than_days = older_than_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+    if older_than_days < 0 {
+        return Err("Trash age must not be negative".to_string());
+    }
+
+    manager
+        .memory_repo()
+        .purge_trash(older_than_days * 24 * 60 * 60)
+        .await
+        .map_err(|e| format!("Failed to purge trash: {}", e))
+}
+
+// === Branch Commands ===
+
+/// List the alternative replies under a message (or the roots when no parent is given)
+#[tauri::command]
+pub async fn list_branches(
+    params: ListBranchesParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<Message>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .list_branches(params.session_id, params.parent_id)
+        .await
+        .map_err(|e| format!("Failed to list branches: {}", e))
+}
+
+/// Make the branch through the given message active
+#[tauri::command]
+pub async fn switch_branch(
+    params: SwitchBranchParams,
+    state: State<'_, DatabaseState>,
+) -> Result<Session, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .switch_branch(params.session_id, params.message_id)
+        .await
+        .map_err(|e| format!("Failed to switch branch: {}", e))
+}
+
+/// Get the linear transcript from the root to the given message, oldest first
+#[tauri::command]
+pub async fn get_transcript(
+    leaf_id: i64,
+    state: State<'_, DatabaseState>,
+) -> Result<Vec<Message>, String> {
+    let manager = database(&state).await?;
+
+    manager
+        .memory_repo()
+        .transcript(leaf_id)
+        .await
+        .map_err(|e| format!("Failed to get transcript: {}", e))
+}
+
 // === Search Commands ===
 
 #[tauri::command]
@@ -268,10 +1365,7 @@ pub async fn semantic_search(
     limit: Option<i64>,
     state: State<'_, DatabaseState>,
 ) -> Result<Vec<Message>, String> {
-    let state_guard = state.lock().await;
-    let manager = state_guard
-        .as_ref()
-        .ok_or("Database not initialized")?;
+    let manager = database(&state).await?;
 
     manager
         .memory_repo()
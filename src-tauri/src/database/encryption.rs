@@ -0,0 +1,140 @@
+//! Encryption at rest with SQLCipher
+//!
+//! SQLCipher derives the page key from the user's passphrase with
+//! PBKDF2-HMAC-SHA512, so the passphrase itself is never stored. An encrypted
+//! file has no plaintext SQLite header, which is how a locked database is
+//! recognised before any passphrase has been given.
+
+use crate::database::{DatabaseError, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{ConnectOptions, Connection};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// First 16 bytes of every plaintext SQLite database
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// SQLite's result code for a file it cannot read as a database
+const SQLITE_NOTADB: &str = "26";
+
+/// Whether the file at `path` exists and is not a plaintext SQLite database
+pub fn is_encrypted(path: &Path) -> Result<bool> {
+    let mut header = [0u8; 16];
+    match File::open(path) {
+        Ok(mut file) => match file.read_exact(&mut header) {
+            Ok(()) => Ok(&header != SQLITE_HEADER),
+            // Empty or truncated files are new databases, not encrypted ones
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e.into()),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Passphrase as a quoted SQL string for `PRAGMA key` and `PRAGMA rekey`
+pub fn key_pragma(passphrase: &str) -> String {
+    format!("'{}'", passphrase.replace('\'', "''"))
+}
+
+/// Whether a query failed because the key doesn't match the file
+pub fn is_wrong_key(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(e) if e.code().as_deref() == Some(SQLITE_NOTADB))
+}
+
+fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.is_empty() {
+        return Err(DatabaseError::Query("Passphrase must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// Open a single connection to `path`, keyed when a passphrase is given
+async fn connect(path: &Path, passphrase: Option<&str>) -> Result<SqliteConnection> {
+    // The create flag is inherited by ATTACH, which has to create the encrypted copy
+    let mut options = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+    if let Some(passphrase) = passphrase {
+        options = options.pragma("key", key_pragma(passphrase));
+    }
+    let mut conn = options
+        .connect()
+        .await
+        .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
+    // A wrong key only shows up once the first page is read
+    if let Err(e) = sqlx::query("SELECT count(*) FROM sqlite_master").fetch_one(&mut conn).await {
+        return Err(if is_wrong_key(&e) && passphrase.is_some() {
+            DatabaseError::WrongPassphrase
+        } else if is_wrong_key(&e) {
+            DatabaseError::Locked
+        } else {
+            e.into()
+        });
+    }
+
+    Ok(conn)
+}
+
+/// Encrypt a plaintext database in place
+///
+/// The database must be closed. Its contents are exported into an encrypted copy
+/// which then replaces the original file.
+pub async fn encrypt_database(path: &Path, passphrase: &str) -> Result<()> {
+    validate_passphrase(passphrase)?;
+    if !path.is_file() {
+        return Err(DatabaseError::Query(format!("Database {} not found", path.display())));
+    }
+    if is_encrypted(path)? {
+        return Err(DatabaseError::Query("Database is already encrypted".to_string()));
+    }
+
+    let encrypted = path.with_extension("encrypting");
+    if encrypted.exists() {
+        fs::remove_file(&encrypted)?;
+    }
+
+    let mut conn = connect(path, None).await?;
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(encrypted.to_string_lossy().as_ref())
+        .bind(passphrase)
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE encrypted").execute(&mut conn).await?;
+    conn.close().await?;
+
+    remove_sidecars(path)?;
+    fs::rename(&encrypted, path)?;
+
+    Ok(())
+}
+
+/// Re-encrypt a closed database under a new passphrase
+pub async fn change_passphrase(path: &Path, current: &str, new: &str) -> Result<()> {
+    validate_passphrase(new)?;
+    if !is_encrypted(path)? {
+        return Err(DatabaseError::Query("Database is not encrypted".to_string()));
+    }
+
+    let mut conn = connect(path, Some(current)).await?;
+    sqlx::query(&format!("PRAGMA rekey = {}", key_pragma(new)))
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    Ok(())
+}
+
+/// Remove WAL and shared-memory files left next to a closed database
+pub fn remove_sidecars(path: &Path) -> Result<()> {
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = PathBuf::from(format!("{}{}", path.display(), suffix));
+        if sidecar.exists() {
+            fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(())
+}
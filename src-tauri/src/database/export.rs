@@ -0,0 +1,355 @@
+//! Session export to Markdown, JSON and self-contained HTML
+//!
+//! Markdown and HTML render the active branch as a readable transcript. JSON is
+//! lossless: it carries the session row and every message on every branch.
+
+use crate::database::models::{ContentPart, Message, MessageRole, Session, SessionQuery};
+use crate::database::{DatabaseError, MemoryRepo, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Identifies exported JSON documents
+const DOCUMENT_FORMAT: &str = "openconverse.session";
+const DOCUMENT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// Lossless JSON export of one session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDocument {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64, // Unix timestamp
+    pub session: Session,
+    pub messages: Vec<Message>, // Every branch, oldest first
+}
+
+/// Metadata written as Markdown front-matter
+#[derive(Serialize)]
+struct FrontMatter<'a> {
+    title: &'a str,
+    session_id: i64,
+    created_at: String,
+    status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    llm_provider: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    goals: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    message_count: usize,
+}
+
+/// Lowercase name with other characters collapsed to '-', for file names
+pub fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "untitled".to_string() } else { slug }
+}
+
+/// Render one session in the given format
+pub async fn export_session(repo: &dyn MemoryRepo, session_id: i64, format: ExportFormat) -> Result<String> {
+    let session = repo.get_session_by_id(session_id).await?;
+
+    if format == ExportFormat::Json {
+        let document = SessionDocument {
+            format: DOCUMENT_FORMAT.to_string(),
+            version: DOCUMENT_VERSION,
+            exported_at: chrono::Utc::now().timestamp(),
+            messages: repo.session_messages(session_id).await?,
+            session,
+        };
+        return serde_json::to_string_pretty(&document).map_err(|e| DatabaseError::Query(e.to_string()));
+    }
+
+    let mut transcript = repo.recent_messages(session_id, None).await?;
+    transcript.reverse();
+
+    match format {
+        ExportFormat::Markdown => render_markdown(&session, &transcript),
+        _ => Ok(render_html(&session, &transcript)),
+    }
+}
+
+/// Export sessions to `dir` as `<id>-<name>.<ext>`, all non-deleted sessions when `session_ids` is `None`
+pub async fn export_sessions(
+    repo: &dyn MemoryRepo,
+    session_ids: Option<Vec<i64>>,
+    format: ExportFormat,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let session_ids = match session_ids {
+        Some(ids) => ids,
+        None => repo
+            .get_sessions(SessionQuery {
+                include_archived: true,
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .map(|session| session.id)
+            .collect(),
+    };
+
+    fs::create_dir_all(dir)?;
+
+    let mut written = Vec::with_capacity(session_ids.len());
+    for session_id in session_ids {
+        let session = repo.get_session_by_id(session_id).await?;
+        let content = export_session(repo, session_id, format).await?;
+        let path = dir.join(format!("{}-{}.{}", session.id, slugify(&session.name), format.extension()));
+        fs::write(&path, content)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+fn format_timestamp(secs: i64) -> String {
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn role_heading(role: MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+        MessageRole::System => "System",
+        MessageRole::Tool => "Tool",
+        MessageRole::Developer => "Developer",
+    }
+}
+
+fn render_markdown(session: &Session, transcript: &[Message]) -> Result<String> {
+    let front_matter = FrontMatter {
+        title: &session.name,
+        session_id: session.id,
+        created_at: format_timestamp(session.created_at),
+        status: session.status.as_str(),
+        llm_provider: session.llm_provider.as_deref(),
+        model_id: session.model_id.as_deref(),
+        role: session.role.as_deref(),
+        goals: session.goals.as_deref(),
+        tags: &session.tags,
+        message_count: transcript.len(),
+    };
+    let yaml = serde_yaml::to_string(&front_matter).map_err(|e| DatabaseError::Query(e.to_string()))?;
+
+    let mut out = format!("---\n{}---\n\n# {}\n", yaml, session.name);
+    for message in transcript {
+        out.push_str(&format!(
+            "\n## {}\n\n_{}_\n\n",
+            role_heading(message.role),
+            format_timestamp(message.ts)
+        ));
+        for part in message.parts() {
+            match part {
+                ContentPart::Text { text } => out.push_str(&format!("{}\n\n", text)),
+                ContentPart::Image { url, alt, .. } => {
+                    out.push_str(&format!("![{}]({})\n\n", alt.unwrap_or_default(), url))
+                }
+                ContentPart::ToolCall { name, arguments, .. } => out.push_str(&format!(
+                    "Tool call `{}`:\n\n```json\n{}\n```\n\n",
+                    name,
+                    serde_json::to_string_pretty(&arguments).unwrap_or_default()
+                )),
+                ContentPart::ToolResult { content, .. } => {
+                    out.push_str(&format!("```\n{}\n```\n\n", content))
+                }
+                ContentPart::Reasoning { text } => out.push_str(&format!(
+                    "<details>\n<summary>Reasoning</summary>\n\n{}\n\n</details>\n\n",
+                    text
+                )),
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Styles are inlined so the file opens anywhere without other assets
+const HTML_STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; max-width: 800px; margin: 2rem auto; padding: 0 1rem; color: #1f2937; background: #f9fafb; }
+header { border-bottom: 1px solid #e5e7eb; margin-bottom: 1.5rem; }
+header dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.25rem 1rem; font-size: 0.9rem; color: #4b5563; }
+.message { background: #fff; border: 1px solid #e5e7eb; border-radius: 8px; padding: 0.75rem 1rem; margin-bottom: 1rem; }
+.message.user { border-left: 4px solid #3b82f6; }
+.message.assistant { border-left: 4px solid #10b981; }
+.message.system, .message.developer, .message.tool { border-left: 4px solid #9ca3af; }
+.meta { font-size: 0.8rem; color: #6b7280; margin-bottom: 0.5rem; }
+.text { white-space: pre-wrap; }
+pre { background: #f3f4f6; padding: 0.5rem; border-radius: 4px; overflow-x: auto; }
+img { max-width: 100%; }
+details { color: #6b7280; margin-bottom: 0.5rem; }
+"#;
+
+/// Image source for the HTML export, or `None` when the image can't be included
+///
+/// Attachments are inlined as `data:` URLs so the export still works on another
+/// machine; other local files are left out, so an export can't leak files from
+/// outside the attachments directory. Only `data:` and `https:` sources are
+/// emitted, so message content can't carry `javascript:` or other schemes into
+/// the page. `https:` images are not fetched: they stay links to the original,
+/// which the page loads when opened.
+fn html_image_src(url: &str, mime_type: Option<&str>) -> Option<String> {
+    let url = url.trim();
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase())
+        .filter(|scheme| scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)));
+
+    let path = match scheme.as_deref() {
+        Some("data") | Some("https") => return Some(url.to_string()),
+        Some("file") => url.trim_start_matches("file://"),
+        // A one-letter scheme is a Windows drive
+        Some(scheme) if scheme.len() > 1 => return None,
+        _ => url,
+    };
+
+    let path = attachment_path(path)?;
+    let mime_type = mime_type
+        .filter(|mime_type| mime_type.starts_with("image/"))
+        .or_else(|| image_mime_type(&path))?;
+    let bytes = fs::read(&path).ok()?;
+    Some(format!("data:{};base64,{}", mime_type, BASE64.encode(bytes)))
+}
+
+/// Canonical path of a local image, or `None` unless it is in the active workspace's attachments
+///
+/// Relative paths are taken from the attachments directory. Resolving symlinks
+/// and `..` first keeps paths that lead out of the directory from passing.
+fn attachment_path(path: &str) -> Option<PathBuf> {
+    let attachments = crate::workspaces::active_workspace().attachments_dir().canonicalize().ok()?;
+    let path = attachments.join(crate::paths::expand_tilde(path)).canonicalize().ok()?;
+    path.starts_with(&attachments).then_some(path)
+}
+
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        _ => return None,
+    })
+}
+
+fn render_html(session: &Session, transcript: &[Message]) -> String {
+    let mut metadata = vec![
+        ("Created", format_timestamp(session.created_at)),
+        ("Status", session.status.to_string()),
+    ];
+    if let Some(model_id) = &session.model_id {
+        metadata.push(("Model", model_id.clone()));
+    }
+    if let Some(goals) = &session.goals {
+        metadata.push(("Goals", goals.clone()));
+    }
+    if !session.tags.is_empty() {
+        metadata.push(("Tags", session.tags.join(", ")));
+    }
+
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<header>\n<h1>{}</h1>\n<dl>\n",
+        escape_html(&session.name),
+        HTML_STYLE,
+        escape_html(&session.name)
+    );
+    for (label, value) in metadata {
+        out.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", label, escape_html(&value)));
+    }
+    out.push_str("</dl>\n</header>\n<main>\n");
+
+    for message in transcript {
+        out.push_str(&format!(
+            "<section class=\"message {}\">\n<div class=\"meta\">{} &middot; {}</div>\n",
+            message.role,
+            role_heading(message.role),
+            format_timestamp(message.ts)
+        ));
+        for part in message.parts() {
+            match part {
+                ContentPart::Text { text } => {
+                    out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(&text)))
+                }
+                ContentPart::Image { url, mime_type, alt } => {
+                    let alt = alt.unwrap_or_default();
+                    match html_image_src(&url, mime_type.as_deref()) {
+                        Some(src) => out.push_str(&format!(
+                            "<img src=\"{}\" alt=\"{}\">\n",
+                            escape_html(&src),
+                            escape_html(&alt)
+                        )),
+                        None => out.push_str(&format!(
+                            "<div class=\"meta\">[Image not included{}]</div>\n",
+                            if alt.is_empty() { String::new() } else { format!(": {}", escape_html(&alt)) }
+                        )),
+                    }
+                }
+                ContentPart::ToolCall { name, arguments, .. } => out.push_str(&format!(
+                    "<div>Tool call <code>{}</code></div>\n<pre>{}</pre>\n",
+                    escape_html(&name),
+                    escape_html(&serde_json::to_string_pretty(&arguments).unwrap_or_default())
+                )),
+                ContentPart::ToolResult { content, .. } => {
+                    out.push_str(&format!("<pre>{}</pre>\n", escape_html(&content)))
+                }
+                ContentPart::Reasoning { text } => out.push_str(&format!(
+                    "<details><summary>Reasoning</summary><div class=\"text\">{}</div></details>\n",
+                    escape_html(&text)
+                )),
+            }
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
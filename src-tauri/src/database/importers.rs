@@ -0,0 +1,438 @@
+//! Importers for conversation archives exported by other chat apps
+//!
+//! ChatGPT's `conversations.json` stores each conversation as a message tree
+//! (`mapping` nodes with `parent`/`children` and a `current_node` leaf); every
+//! branch is kept and the current node becomes the active leaf. Claude's export
+//! stores a flat `chat_messages` list, linked by `parent_message_uuid` in newer
+//! archives. Original titles, timestamps and model names are preserved, and each
+//! session remembers its source id so importing the same archive twice is a no-op.
+
+use crate::database::models::{
+    ContentPart, CreateMessage, CreateSession, ImportedConversation, ImportedMessage, MessageRole,
+};
+use crate::database::{DatabaseError, MemoryRepo, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name both apps use inside their export archives
+const CONVERSATIONS_FILE: &str = "conversations.json";
+
+const UNTITLED: &str = "Imported conversation";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    ChatGpt,
+    Claude,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::Claude => "claude",
+        }
+    }
+
+    /// Provider recorded on imported sessions
+    fn llm_provider(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "openai",
+            ImportSource::Claude => "anthropic",
+        }
+    }
+}
+
+/// Reported after each conversation of an archive is processed
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub source: ImportSource,
+    pub processed: usize,
+    pub total: usize,
+    pub imported: usize,
+    pub skipped: usize, // Already imported earlier
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub messages: usize,
+    pub session_ids: Vec<i64>,
+}
+
+/// Import every conversation in an export, calling `on_progress` after each one
+///
+/// `path` may point at `conversations.json` itself or at the unpacked archive
+/// directory containing it.
+pub async fn import_conversations(
+    repo: &dyn MemoryRepo,
+    source: ImportSource,
+    path: &Path,
+    mut on_progress: impl FnMut(&ImportProgress) + Send,
+) -> Result<ImportReport> {
+    let path = resolve_archive_path(path);
+    let json = fs::read_to_string(&path)?;
+    let conversations = match source {
+        ImportSource::ChatGpt => parse_chatgpt(&json),
+        ImportSource::Claude => parse_claude(&json),
+    }
+    .map_err(|message| DatabaseError::InvalidFile {
+        path: path.display().to_string(),
+        message,
+    })?;
+
+    let mut report = ImportReport::default();
+    let mut progress = ImportProgress {
+        source,
+        processed: 0,
+        total: conversations.len(),
+        imported: 0,
+        skipped: 0,
+    };
+
+    for conversation in conversations {
+        let message_count = conversation.messages.len();
+        match repo.import_conversation(conversation).await? {
+            Some(session) => {
+                report.imported += 1;
+                report.messages += message_count;
+                report.session_ids.push(session.id);
+            }
+            None => report.skipped += 1,
+        }
+
+        progress.processed += 1;
+        progress.imported = report.imported;
+        progress.skipped = report.skipped;
+        on_progress(&progress);
+    }
+
+    Ok(report)
+}
+
+fn resolve_archive_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(CONVERSATIONS_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Parse ChatGPT's `conversations.json`
+pub fn parse_chatgpt(json: &str) -> std::result::Result<Vec<ImportedConversation>, String> {
+    let archive: Vec<Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    archive.iter().map(chatgpt_conversation).collect()
+}
+
+fn chatgpt_conversation(conversation: &Value) -> std::result::Result<ImportedConversation, String> {
+    let external_id = conversation
+        .get("conversation_id")
+        .or_else(|| conversation.get("id"))
+        .and_then(Value::as_str)
+        .ok_or("Conversation without an id")?
+        .to_string();
+    let created_ms = conversation.get("create_time").and_then(seconds_to_ms).unwrap_or(0);
+    let mapping = conversation
+        .get("mapping")
+        .and_then(Value::as_object)
+        .ok_or_else(|| format!("Conversation {} has no message mapping", external_id))?;
+
+    // Roots are nodes whose parent is missing from the mapping
+    let mut roots: Vec<&String> = mapping
+        .iter()
+        .filter(|(_, node)| {
+            node.get("parent")
+                .and_then(Value::as_str)
+                .is_none_or(|parent| !mapping.contains_key(parent))
+        })
+        .map(|(id, _)| id)
+        .collect();
+    roots.sort();
+
+    // Depth-first so parents are inserted before children; nodes that carry no
+    // visible message are skipped and their children hang off the nearest kept ancestor
+    let mut messages: Vec<ImportedMessage> = Vec::new();
+    let mut node_index: HashMap<&str, Option<usize>> = HashMap::new();
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<(&str, Option<usize>, i64)> =
+        roots.into_iter().rev().map(|id| (id.as_str(), None, created_ms)).collect();
+    let mut model_id: Option<String> = None;
+
+    while let Some((node_id, parent, parent_ms)) = stack.pop() {
+        if !visited.insert(node_id) {
+            continue;
+        }
+        let Some(node) = mapping.get(node_id) else { continue };
+
+        let mut index = parent;
+        let mut ts_ms = parent_ms;
+        if let Some(imported) = node.get("message").and_then(|message| chatgpt_message(message, parent, parent_ms)) {
+            ts_ms = imported.ts_ms;
+            messages.push(imported);
+            index = Some(messages.len() - 1);
+        }
+        node_index.insert(node_id, index);
+
+        if let Some(slug) = node.pointer("/message/metadata/model_slug").and_then(Value::as_str) {
+            model_id = Some(slug.to_string());
+        }
+
+        if let Some(children) = node.get("children").and_then(Value::as_array) {
+            for child in children.iter().rev().filter_map(Value::as_str) {
+                stack.push((child, index, ts_ms));
+            }
+        }
+    }
+
+    let active_leaf = conversation
+        .get("current_node")
+        .and_then(Value::as_str)
+        .and_then(|node_id| node_index.get(node_id).copied().flatten());
+    let model_id = conversation
+        .get("default_model_slug")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or(model_id);
+
+    Ok(ImportedConversation {
+        source: ImportSource::ChatGpt.as_str().to_string(),
+        external_id,
+        session: imported_session(conversation.get("title"), ImportSource::ChatGpt, model_id),
+        created_at: created_ms / 1000,
+        messages,
+        active_leaf,
+    })
+}
+
+fn chatgpt_message(message: &Value, parent: Option<usize>, parent_ms: i64) -> Option<ImportedMessage> {
+    let role = match message.pointer("/author/role").and_then(Value::as_str)? {
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        "system" => MessageRole::System,
+        "tool" => MessageRole::Tool,
+        _ => return None,
+    };
+    if message
+        .pointer("/metadata/is_visually_hidden_from_conversation")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let content = message.get("content")?;
+    let mut parts = Vec::new();
+    match content.get("content_type").and_then(Value::as_str).unwrap_or("text") {
+        "thoughts" => {
+            for thought in content.get("thoughts").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(text) = thought.get("content").and_then(Value::as_str) {
+                    push_text(&mut parts, text, true);
+                }
+            }
+        }
+        _ => {
+            for part in content.get("parts").and_then(Value::as_array).into_iter().flatten() {
+                match part {
+                    Value::String(text) => push_text(&mut parts, text, false),
+                    Value::Object(object) => {
+                        if let Some(url) = object.get("asset_pointer").and_then(Value::as_str) {
+                            parts.push(ContentPart::Image {
+                                url: url.to_string(),
+                                mime_type: None,
+                                alt: None,
+                            });
+                        } else if let Some(text) = object.get("text").and_then(Value::as_str) {
+                            push_text(&mut parts, text, false);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Code, execution output and quotes keep their text outside `parts`
+            if parts.is_empty() {
+                if let Some(text) = content.get("text").and_then(Value::as_str) {
+                    push_text(&mut parts, text, false);
+                }
+            }
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let ts_ms = message.get("create_time").and_then(seconds_to_ms).unwrap_or(parent_ms);
+    Some(ImportedMessage {
+        message: imported_message(role, parts),
+        parent,
+        ts_ms,
+    })
+}
+
+/// Parse Claude's `conversations.json`
+pub fn parse_claude(json: &str) -> std::result::Result<Vec<ImportedConversation>, String> {
+    let archive: Vec<Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    archive.iter().map(claude_conversation).collect()
+}
+
+fn claude_conversation(conversation: &Value) -> std::result::Result<ImportedConversation, String> {
+    let external_id = conversation
+        .get("uuid")
+        .and_then(Value::as_str)
+        .ok_or("Conversation without a uuid")?
+        .to_string();
+    let created_ms = conversation.get("created_at").and_then(iso_to_ms).unwrap_or(0);
+
+    let mut messages: Vec<ImportedMessage> = Vec::new();
+    let mut index_by_uuid: HashMap<&str, usize> = HashMap::new();
+    let mut previous_ms = created_ms;
+
+    for chat_message in conversation.get("chat_messages").and_then(Value::as_array).into_iter().flatten() {
+        let role = match chat_message.get("sender").and_then(Value::as_str) {
+            Some("human") => MessageRole::User,
+            Some("assistant") => MessageRole::Assistant,
+            _ => continue,
+        };
+        let parts = claude_parts(chat_message);
+        if parts.is_empty() {
+            continue;
+        }
+
+        // Older exports are linear; newer ones link each message to its parent
+        let parent = match chat_message.get("parent_message_uuid").and_then(Value::as_str) {
+            Some(parent_uuid) => index_by_uuid.get(parent_uuid).copied(),
+            None => messages.len().checked_sub(1),
+        }
+        .or_else(|| messages.len().checked_sub(1));
+        let ts_ms = chat_message.get("created_at").and_then(iso_to_ms).unwrap_or(previous_ms);
+        previous_ms = ts_ms;
+
+        if let Some(uuid) = chat_message.get("uuid").and_then(Value::as_str) {
+            index_by_uuid.insert(uuid, messages.len());
+        }
+        messages.push(ImportedMessage {
+            message: imported_message(role, parts),
+            parent,
+            ts_ms,
+        });
+    }
+
+    let model_id = conversation.get("model").and_then(Value::as_str).map(str::to_string);
+
+    Ok(ImportedConversation {
+        source: ImportSource::Claude.as_str().to_string(),
+        external_id,
+        session: imported_session(conversation.get("name"), ImportSource::Claude, model_id),
+        created_at: created_ms / 1000,
+        active_leaf: messages.len().checked_sub(1),
+        messages,
+    })
+}
+
+fn claude_parts(chat_message: &Value) -> Vec<ContentPart> {
+    let mut parts = Vec::new();
+    for block in chat_message.get("content").and_then(Value::as_array).into_iter().flatten() {
+        match block.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    push_text(&mut parts, text, false);
+                }
+            }
+            Some("thinking") => {
+                if let Some(text) = block.get("thinking").and_then(Value::as_str) {
+                    push_text(&mut parts, text, true);
+                }
+            }
+            Some("tool_use") => parts.push(ContentPart::ToolCall {
+                id: block.get("id").and_then(Value::as_str).unwrap_or_default().to_string(),
+                name: block.get("name").and_then(Value::as_str).unwrap_or_default().to_string(),
+                arguments: block.get("input").cloned().unwrap_or(Value::Null),
+            }),
+            Some("tool_result") => {
+                let content = match block.get("content") {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(Value::Array(items)) => items
+                        .iter()
+                        .filter_map(|item| item.get("text").and_then(Value::as_str))
+                        .collect::<Vec<_>>()
+                        .join("\n\n"),
+                    _ => String::new(),
+                };
+                parts.push(ContentPart::ToolResult {
+                    tool_call_id: block.get("tool_use_id").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    content,
+                    is_error: block.get("is_error").and_then(Value::as_bool).unwrap_or(false),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Older exports only carry the flattened text
+    if parts.is_empty() {
+        if let Some(text) = chat_message.get("text").and_then(Value::as_str) {
+            push_text(&mut parts, text, false);
+        }
+    }
+    parts
+}
+
+fn push_text(parts: &mut Vec<ContentPart>, text: &str, reasoning: bool) {
+    if text.trim().is_empty() {
+        return;
+    }
+    let text = text.to_string();
+    parts.push(if reasoning { ContentPart::Reasoning { text } } else { ContentPart::Text { text } });
+}
+
+fn imported_session(title: Option<&Value>, source: ImportSource, model_id: Option<String>) -> CreateSession {
+    let name = title
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(UNTITLED);
+
+    CreateSession {
+        name: name.to_string(),
+        role: None,
+        goals: None,
+        llm_provider: Some(source.llm_provider().to_string()),
+        model_id,
+        status: None,
+        persona_id: None,
+    }
+}
+
+fn imported_message(role: MessageRole, parts: Vec<ContentPart>) -> CreateMessage {
+    // Plain single-text messages are stored without structured parts
+    let (content, content_parts) = match parts.as_slice() {
+        [ContentPart::Text { text }] => (text.clone(), None),
+        _ => (ContentPart::plain_text(&parts), Some(parts)),
+    };
+
+    CreateMessage {
+        session_id: 0,
+        role,
+        content,
+        embedding: None,
+        recall_score: None,
+        parent_id: None,
+        token_count: None,
+        content_parts,
+        reasoning_tokens: None,
+        generation_params: None,
+    }
+}
+
+fn seconds_to_ms(value: &Value) -> Option<i64> {
+    value.as_f64().map(|seconds| (seconds * 1000.0).round() as i64)
+}
+
+fn iso_to_ms(value: &Value) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(value.as_str()?)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
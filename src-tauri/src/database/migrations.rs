@@ -5,7 +5,7 @@
 /// to add new migrations as the schema evolves.
 
 /// Migration trait for defining database schema changes
-pub trait Migration {
+pub trait Migration: Send + Sync {
     fn version(&self) -> i32;
     fn description(&self) -> &str;
     fn up_sql(&self) -> &str;
@@ -13,6 +13,10 @@ pub trait Migration {
 }
 
 /// Initial migration to create the core memory tables
+///
+/// This is the legacy three-table schema from early builds. It is no longer
+/// applied; existing data is moved by `SqliteProvider::import_legacy_tables`.
+/// Version 2 is the session/message core schema created by `SqliteProvider::migrate`.
 pub struct InitialMigration;
 
 impl Migration for InitialMigration {
@@ -79,15 +83,547 @@ impl Migration for InitialMigration {
     }
 }
 
+/// Add millisecond message timestamps for stable ordering and cursor pagination
+pub struct MessageTimestampMsMigration;
+
+impl Migration for MessageTimestampMsMigration {
+    fn version(&self) -> i32 {
+        3
+    }
+
+    fn description(&self) -> &str {
+        "Add millisecond timestamps to messages"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE message ADD COLUMN ts_ms INTEGER NOT NULL DEFAULT 0;
+        UPDATE message SET ts_ms = ts * 1000;
+        CREATE INDEX IF NOT EXISTS idx_message_session_ts_ms ON message(session_id, ts_ms, id);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_message_session_ts_ms;
+        ALTER TABLE message DROP COLUMN ts_ms;
+        "#)
+    }
+}
+
+/// Keep prior versions of edited or regenerated messages
+pub struct MessageRevisionMigration;
+
+impl Migration for MessageRevisionMigration {
+    fn version(&self) -> i32 {
+        4
+    }
+
+    fn description(&self) -> &str {
+        "Create message revision history"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS message_revision (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            changed_by TEXT,
+            revised_at INTEGER NOT NULL,
+            FOREIGN KEY (message_id) REFERENCES message(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_message_revision_message_id ON message_revision(message_id, revised_at);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some("DROP TABLE IF EXISTS message_revision;")
+    }
+}
+
+/// Link messages into a tree so edits and regenerations become sibling branches
+///
+/// Existing sessions are linked linearly in `(ts_ms, id)` order and their
+/// newest message becomes the active leaf.
+pub struct MessageTreeMigration;
+
+impl Migration for MessageTreeMigration {
+    fn version(&self) -> i32 {
+        5
+    }
+
+    fn description(&self) -> &str {
+        "Add parent links to messages and active branch to sessions"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE message ADD COLUMN parent_id INTEGER REFERENCES message(id) ON DELETE SET NULL;
+        ALTER TABLE session ADD COLUMN active_leaf_id INTEGER;
+        UPDATE message SET parent_id = (
+            SELECT prev.id FROM message prev
+            WHERE prev.session_id = message.session_id
+              AND (prev.ts_ms, prev.id) < (message.ts_ms, message.id)
+            ORDER BY prev.ts_ms DESC, prev.id DESC
+            LIMIT 1
+        );
+        UPDATE session SET active_leaf_id = (
+            SELECT id FROM message
+            WHERE message.session_id = session.id
+            ORDER BY ts_ms DESC, id DESC
+            LIMIT 1
+        );
+        CREATE INDEX IF NOT EXISTS idx_message_parent_id ON message(parent_id);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_message_parent_id;
+        ALTER TABLE session DROP COLUMN active_leaf_id;
+        ALTER TABLE message DROP COLUMN parent_id;
+        "#)
+    }
+}
+
+/// Record the source session and message of forked sessions
+pub struct SessionForkMigration;
+
+impl Migration for SessionForkMigration {
+    fn version(&self) -> i32 {
+        6
+    }
+
+    fn description(&self) -> &str {
+        "Add fork origin to sessions"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE session ADD COLUMN forked_from_session_id INTEGER REFERENCES session(id) ON DELETE SET NULL;
+        ALTER TABLE session ADD COLUMN forked_from_message_id INTEGER REFERENCES message(id) ON DELETE SET NULL;
+        CREATE INDEX IF NOT EXISTS idx_session_forked_from ON session(forked_from_session_id);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_session_forked_from;
+        ALTER TABLE session DROP COLUMN forked_from_message_id;
+        ALTER TABLE session DROP COLUMN forked_from_session_id;
+        "#)
+    }
+}
+
+/// Soft deletion: trashed sessions and messages keep their rows until purged
+pub struct SoftDeleteMigration;
+
+impl Migration for SoftDeleteMigration {
+    fn version(&self) -> i32 {
+        7
+    }
+
+    fn description(&self) -> &str {
+        "Add soft delete to sessions and messages"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE session ADD COLUMN deleted_at INTEGER;
+        ALTER TABLE message ADD COLUMN deleted_at INTEGER;
+        CREATE INDEX IF NOT EXISTS idx_session_deleted_at ON session(deleted_at);
+        CREATE INDEX IF NOT EXISTS idx_message_deleted_at ON message(deleted_at);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_message_deleted_at;
+        DROP INDEX IF EXISTS idx_session_deleted_at;
+        ALTER TABLE message DROP COLUMN deleted_at;
+        ALTER TABLE session DROP COLUMN deleted_at;
+        "#)
+    }
+}
+
+/// Session organisation: folders, many-to-many tags and pinning
+pub struct SessionOrganisationMigration;
+
+impl Migration for SessionOrganisationMigration {
+    fn version(&self) -> i32 {
+        8
+    }
+
+    fn description(&self) -> &str {
+        "Add folders, tags and pinning to sessions"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS folder (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            created_at INTEGER NOT NULL DEFAULT (unixepoch())
+        );
+        CREATE TABLE IF NOT EXISTS tag (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+        CREATE TABLE IF NOT EXISTS session_tag (
+            session_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (session_id, tag_id),
+            FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE
+        );
+        ALTER TABLE session ADD COLUMN folder_id INTEGER REFERENCES folder(id) ON DELETE SET NULL;
+        ALTER TABLE session ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+        CREATE INDEX IF NOT EXISTS idx_session_folder_id ON session(folder_id);
+        CREATE INDEX IF NOT EXISTS idx_session_tag_tag_id ON session_tag(tag_id);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_session_tag_tag_id;
+        DROP INDEX IF EXISTS idx_session_folder_id;
+        ALTER TABLE session DROP COLUMN pinned;
+        ALTER TABLE session DROP COLUMN folder_id;
+        DROP TABLE IF EXISTS session_tag;
+        DROP TABLE IF EXISTS tag;
+        DROP TABLE IF EXISTS folder;
+        "#)
+    }
+}
+
+/// Token counts on messages and an index for per-session activity summaries
+pub struct SessionActivityMigration;
+
+impl Migration for SessionActivityMigration {
+    fn version(&self) -> i32 {
+        9
+    }
+
+    fn description(&self) -> &str {
+        "Add message token counts and session activity index"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE message ADD COLUMN token_count INTEGER;
+        CREATE INDEX IF NOT EXISTS idx_message_session_activity
+            ON message(session_id, ts_ms, id, token_count)
+            WHERE deleted_at IS NULL;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_message_session_activity;
+        ALTER TABLE message DROP COLUMN token_count;
+        "#)
+    }
+}
+
+/// Map free-text session statuses onto the open/closed/archived state machine
+pub struct SessionStatusMigration;
+
+impl Migration for SessionStatusMigration {
+    fn version(&self) -> i32 {
+        10
+    }
+
+    fn description(&self) -> &str {
+        "Normalize session status values"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        UPDATE session SET status = lower(trim(status));
+        UPDATE session SET status = 'open' WHERE status NOT IN ('open', 'closed', 'archived');
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Allow `tool` and `developer` message roles
+///
+/// SQLite cannot alter a CHECK constraint, so the message table is rebuilt
+/// with the same columns and its indexes are recreated.
+pub struct MessageRoleMigration;
+
+impl Migration for MessageRoleMigration {
+    fn version(&self) -> i32 {
+        11
+    }
+
+    fn description(&self) -> &str {
+        "Add tool and developer message roles"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        CREATE TABLE message_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id INTEGER NOT NULL,
+            role TEXT NOT NULL CHECK (role IN ('user', 'assistant', 'system', 'tool', 'developer')),
+            content TEXT NOT NULL,
+            ts INTEGER NOT NULL DEFAULT (unixepoch()),
+            embedding BLOB,
+            recall_score REAL,
+            ts_ms INTEGER NOT NULL DEFAULT 0,
+            parent_id INTEGER REFERENCES message(id) ON DELETE SET NULL,
+            deleted_at INTEGER,
+            token_count INTEGER,
+            FOREIGN KEY (session_id) REFERENCES session(id) ON DELETE CASCADE
+        );
+        INSERT INTO message_new (id, session_id, role, content, ts, embedding, recall_score, ts_ms, parent_id, deleted_at, token_count)
+            SELECT id, session_id, role, content, ts, embedding, recall_score, ts_ms, parent_id, deleted_at, token_count FROM message;
+        DROP TABLE message;
+        ALTER TABLE message_new RENAME TO message;
+        CREATE INDEX IF NOT EXISTS idx_message_session_id ON message(session_id);
+        CREATE INDEX IF NOT EXISTS idx_message_ts ON message(ts);
+        CREATE INDEX IF NOT EXISTS idx_message_role ON message(role);
+        CREATE INDEX IF NOT EXISTS idx_message_session_ts_ms ON message(session_id, ts_ms, id);
+        CREATE INDEX IF NOT EXISTS idx_message_parent_id ON message(parent_id);
+        CREATE INDEX IF NOT EXISTS idx_message_deleted_at ON message(deleted_at);
+        CREATE INDEX IF NOT EXISTS idx_message_session_activity
+            ON message(session_id, ts_ms, id, token_count)
+            WHERE deleted_at IS NULL;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        // Narrowing the CHECK would fail on stored tool/developer messages
+        None
+    }
+}
+
+/// Structured message content stored as JSON next to the plain-text column
+pub struct MessageContentPartsMigration;
+
+impl Migration for MessageContentPartsMigration {
+    fn version(&self) -> i32 {
+        12
+    }
+
+    fn description(&self) -> &str {
+        "Add structured message content parts"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE message ADD COLUMN content_parts TEXT;
+        ALTER TABLE message_revision ADD COLUMN content_parts TEXT;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        ALTER TABLE message_revision DROP COLUMN content_parts;
+        ALTER TABLE message DROP COLUMN content_parts;
+        "#)
+    }
+}
+
+/// Reasoning token counts per message and the per-session opt-in to resend reasoning
+pub struct ReasoningMigration;
+
+impl Migration for ReasoningMigration {
+    fn version(&self) -> i32 {
+        13
+    }
+
+    fn description(&self) -> &str {
+        "Add reasoning token counts and session reasoning setting"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE message ADD COLUMN reasoning_tokens INTEGER;
+        ALTER TABLE session ADD COLUMN include_reasoning INTEGER NOT NULL DEFAULT 0;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        ALTER TABLE session DROP COLUMN include_reasoning;
+        ALTER TABLE message DROP COLUMN reasoning_tokens;
+        "#)
+    }
+}
+
+/// Per-session sampling parameters, and the parameters each reply was generated with
+pub struct GenerationParamsMigration;
+
+impl Migration for GenerationParamsMigration {
+    fn version(&self) -> i32 {
+        14
+    }
+
+    fn description(&self) -> &str {
+        "Add session and message generation parameters"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE session ADD COLUMN generation_params TEXT NOT NULL DEFAULT '{}';
+        ALTER TABLE message ADD COLUMN generation_params TEXT;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        ALTER TABLE message DROP COLUMN generation_params;
+        ALTER TABLE session DROP COLUMN generation_params;
+        "#)
+    }
+}
+
+/// Persona library that sessions can be started from
+pub struct PersonaMigration;
+
+impl Migration for PersonaMigration {
+    fn version(&self) -> i32 {
+        15
+    }
+
+    fn description(&self) -> &str {
+        "Create persona library"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        CREATE TABLE IF NOT EXISTS persona (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            system_prompt TEXT,
+            goals TEXT,
+            llm_provider TEXT,
+            model_id TEXT,
+            generation_params TEXT NOT NULL DEFAULT '{}',
+            avatar TEXT,
+            created_at INTEGER NOT NULL DEFAULT (unixepoch()),
+            updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+        );
+        ALTER TABLE session ADD COLUMN persona_id INTEGER REFERENCES persona(id) ON DELETE SET NULL;
+        CREATE INDEX IF NOT EXISTS idx_session_persona_id ON session(persona_id);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_session_persona_id;
+        ALTER TABLE session DROP COLUMN persona_id;
+        DROP TABLE IF EXISTS persona;
+        "#)
+    }
+}
+
+/// Remember where imported sessions came from so re-imports can skip them
+pub struct SessionImportMigration;
+
+impl Migration for SessionImportMigration {
+    fn version(&self) -> i32 {
+        16
+    }
+
+    fn description(&self) -> &str {
+        "Track imported session sources"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        ALTER TABLE session ADD COLUMN import_source TEXT;
+        ALTER TABLE session ADD COLUMN import_id TEXT;
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_session_import
+            ON session(import_source, import_id)
+            WHERE import_source IS NOT NULL;
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        Some(r#"
+        DROP INDEX IF EXISTS idx_session_import;
+        ALTER TABLE session DROP COLUMN import_id;
+        ALTER TABLE session DROP COLUMN import_source;
+        "#)
+    }
+}
+
+/// Remove rows orphaned while foreign keys were not enforced
+///
+/// Older builds deleted sessions without cascading, leaving messages, revisions
+/// and tag links behind and references pointing at rows that no longer exist.
+pub struct OrphanCleanupMigration;
+
+impl Migration for OrphanCleanupMigration {
+    fn version(&self) -> i32 {
+        17
+    }
+
+    fn description(&self) -> &str {
+        "Remove orphaned rows"
+    }
+
+    fn up_sql(&self) -> &str {
+        r#"
+        DELETE FROM message WHERE session_id NOT IN (SELECT id FROM session);
+        DELETE FROM message_revision WHERE message_id NOT IN (SELECT id FROM message);
+        DELETE FROM session_tag
+            WHERE session_id NOT IN (SELECT id FROM session) OR tag_id NOT IN (SELECT id FROM tag);
+
+        UPDATE message SET parent_id = NULL
+            WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM message);
+        UPDATE session SET active_leaf_id = NULL
+            WHERE active_leaf_id IS NOT NULL AND active_leaf_id NOT IN (SELECT id FROM message);
+        UPDATE session SET forked_from_session_id = NULL
+            WHERE forked_from_session_id IS NOT NULL AND forked_from_session_id NOT IN (SELECT id FROM session);
+        UPDATE session SET forked_from_message_id = NULL
+            WHERE forked_from_message_id IS NOT NULL AND forked_from_message_id NOT IN (SELECT id FROM message);
+        UPDATE session SET folder_id = NULL
+            WHERE folder_id IS NOT NULL AND folder_id NOT IN (SELECT id FROM folder);
+        UPDATE session SET persona_id = NULL
+            WHERE persona_id IS NOT NULL AND persona_id NOT IN (SELECT id FROM persona);
+        "#
+    }
+
+    fn down_sql(&self) -> Option<&str> {
+        // Deleted orphans cannot be brought back
+        None
+    }
+}
+
 /// Migration runner that applies pending migrations
 pub struct MigrationRunner {
     migrations: Vec<Box<dyn Migration>>,
 }
 
 impl MigrationRunner {
+    /// Create a runner with every migration applied on top of the core schema
     pub fn new() -> Self {
         Self {
-            migrations: vec![Box::new(InitialMigration)],
+            migrations: vec![
+                Box::new(MessageTimestampMsMigration),
+                Box::new(MessageRevisionMigration),
+                Box::new(MessageTreeMigration),
+                Box::new(SessionForkMigration),
+                Box::new(SoftDeleteMigration),
+                Box::new(SessionOrganisationMigration),
+                Box::new(SessionActivityMigration),
+                Box::new(SessionStatusMigration),
+                Box::new(MessageRoleMigration),
+                Box::new(MessageContentPartsMigration),
+                Box::new(ReasoningMigration),
+                Box::new(GenerationParamsMigration),
+                Box::new(PersonaMigration),
+                Box::new(SessionImportMigration),
+                Box::new(OrphanCleanupMigration),
+            ],
         }
     }
 
@@ -12,13 +12,19 @@ pub mod providers;
 pub mod models;
 pub mod migrations;
 pub mod commands;
+pub mod persona_files;
+pub mod export;
+pub mod importers;
+pub mod backup;
+pub mod encryption;
 
 #[cfg(test)]
 pub mod tests;
 
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
-use models::{Session, Message, CreateSession, CreateMessage, DatabaseStats};
+use models::{Session, Message, CreateSession, CreateMessage, DatabaseStats, LegacyImportReport, MessageCursor, MessagePage, UpdateSession, UpdateMessage, MessageRevision, TrashContents, PurgeReport, SessionQuery, SessionSummary, SessionStatus, Folder, Persona, CreatePersona, UpdatePersona, ImportedConversation};
 use crate::connectors::openrouter::OpenRouterConnector;
 use crate::connectors::settings::SettingsManager;
 use crate::connectors::Connector;
@@ -35,23 +41,73 @@ pub enum DatabaseError {
     Io(#[from] std::io::Error),
     #[error("SQLx error: {0}")]
     Sqlx(#[from] sqlx::Error),
+    #[error("Session {session_id} is {status} and does not accept message changes")]
+    SessionReadOnly { session_id: i64, status: SessionStatus },
+    #[error("Cannot change session status from {from} to {to}")]
+    InvalidStatusTransition { from: SessionStatus, to: SessionStatus },
+    #[error("Invalid generation parameters: {0}")]
+    InvalidGenerationParams(String),
+    #[error("Invalid file {path}: {message}")]
+    InvalidFile { path: String, message: String },
+    #[error("Database is encrypted and locked; unlock it with its passphrase")]
+    Locked,
+    #[error("Passphrase does not unlock the database")]
+    WrongPassphrase,
 }
 
 pub type Result<T> = std::result::Result<T, DatabaseError>;
 
 /// Memory repository trait for database operations
 #[async_trait::async_trait]
-pub trait MemoryRepo {
+pub trait MemoryRepo: Send + Sync {
     // Session operations
     async fn create_session(&self, session: CreateSession) -> Result<Session>;
-    async fn get_sessions(&self) -> Result<Vec<Session>>;
+    async fn get_sessions(&self, query: SessionQuery) -> Result<Vec<Session>>;
+    async fn session_summaries(&self, query: SessionQuery) -> Result<Vec<SessionSummary>>;
     async fn get_session_by_id(&self, session_id: i64) -> Result<Session>;
+    async fn update_session(&self, session_id: i64, update: UpdateSession) -> Result<Session>;
+    async fn fork_session(&self, session_id: i64, message_id: i64) -> Result<Session>;
     async fn delete_session(&self, session_id: i64) -> Result<bool>;
+    async fn restore_session(&self, session_id: i64) -> Result<Session>;
+    async fn import_conversation(&self, conversation: ImportedConversation) -> Result<Option<Session>>;
+
+    // Session organisation operations
+    async fn create_folder(&self, name: String) -> Result<Folder>;
+    async fn list_folders(&self) -> Result<Vec<Folder>>;
+    async fn delete_folder(&self, folder_id: i64) -> Result<bool>;
+    async fn set_session_folder(&self, session_id: i64, folder_id: Option<i64>) -> Result<Session>;
+    async fn set_session_tags(&self, session_id: i64, tags: Vec<String>) -> Result<Session>;
+    async fn list_tags(&self) -> Result<Vec<String>>;
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<Session>;
+    async fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session>;
+
+    // Persona operations
+    async fn create_persona(&self, persona: CreatePersona) -> Result<Persona>;
+    async fn upsert_persona(&self, persona: CreatePersona) -> Result<Persona>;
+    async fn list_personas(&self) -> Result<Vec<Persona>>;
+    async fn get_persona(&self, persona_id: i64) -> Result<Persona>;
+    async fn update_persona(&self, persona_id: i64, update: UpdatePersona, apply_to_sessions: bool) -> Result<Persona>;
+    async fn delete_persona(&self, persona_id: i64) -> Result<bool>;
 
     // Message operations
     async fn save_message(&self, message: CreateMessage) -> Result<Message>;
     async fn recent_messages(&self, session_id: i64, limit: Option<i64>) -> Result<Vec<Message>>;
+    async fn session_messages(&self, session_id: i64) -> Result<Vec<Message>>;
+    async fn messages_page(&self, session_id: i64, cursor: MessageCursor) -> Result<MessagePage>;
+    async fn update_message(&self, message_id: i64, update: UpdateMessage) -> Result<Message>;
     async fn delete_message(&self, message_id: i64) -> Result<bool>;
+    async fn restore_message(&self, message_id: i64) -> Result<Message>;
+    async fn list_message_revisions(&self, message_id: i64) -> Result<Vec<MessageRevision>>;
+    async fn restore_message_revision(&self, revision_id: i64) -> Result<Message>;
+
+    // Branch operations
+    async fn list_branches(&self, session_id: i64, parent_id: Option<i64>) -> Result<Vec<Message>>;
+    async fn switch_branch(&self, session_id: i64, message_id: i64) -> Result<Session>;
+    async fn transcript(&self, leaf_id: i64) -> Result<Vec<Message>>;
+
+    // Trash operations
+    async fn list_trash(&self) -> Result<TrashContents>;
+    async fn purge_trash(&self, older_than_secs: i64) -> Result<PurgeReport>;
 
     // Vector search operations
     async fn semantic_search(&self, query_embedding: Vec<f32>, limit: Option<i64>) -> Result<Vec<Message>>;
@@ -62,10 +118,116 @@ pub trait MemoryRepo {
 }
 
 /// Database configuration
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DatabaseConfig {
     pub provider: DatabaseProvider,
     pub connection_string: String,
+    pub passphrase: Option<String>, // Opens a SQLCipher-encrypted database
+    pub pool: PoolOptions,
+}
+
+impl DatabaseConfig {
+    /// Configuration for the backend selected in a `memory_config` value
+    ///
+    /// SQLite reads its file from `config.path`, defaulting to `default_db_path()`.
+    /// A leading `~/` is expanded and relative paths are inside the data directory.
+    pub fn from_memory_config(memory_config: &serde_json::Value) -> Result<Self> {
+        let provider = memory_config
+            .get("provider")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("sqlite");
+        if provider != "sqlite" {
+            return Err(DatabaseError::Connection(format!("Unsupported memory provider: {}", provider)));
+        }
+
+        let connection_string = memory_config
+            .pointer("/config/path")
+            .and_then(serde_json::Value::as_str)
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(resolve_db_path)
+            .unwrap_or_else(|| DatabaseManager::default_db_path().to_string_lossy().to_string());
+
+        Ok(Self {
+            provider: DatabaseProvider::SQLite,
+            connection_string,
+            passphrase: None,
+            pool: PoolOptions::from_memory_config(memory_config),
+        })
+    }
+
+    /// Configuration from the saved settings
+    pub fn from_settings() -> Result<Self> {
+        let settings = crate::settings::load_settings()
+            .map_err(|e| DatabaseError::Connection(format!("Failed to load settings: {}", e)))?;
+        Self::from_memory_config(&settings.memory_config)
+    }
+}
+
+fn resolve_db_path(path: &str) -> String {
+    if path == ":memory:" || path.starts_with("sqlite:") {
+        return path.to_string();
+    }
+    let path = crate::paths::expand_tilde(path);
+    let path = if path.is_absolute() { path } else { crate::paths::data_dir().join(path) };
+    path.to_string_lossy().to_string()
+}
+
+/// Connection pool tuning, read from `memory_config.config` in the settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolOptions {
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    #[serde(default)]
+    pub min_connections: u32,
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64, // How long a connection waits for a lock before failing
+}
+
+fn default_max_connections() -> u32 {
+    5
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5_000
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: default_max_connections(),
+            min_connections: 0,
+            busy_timeout_ms: default_busy_timeout_ms(),
+        }
+    }
+}
+
+impl PoolOptions {
+    /// Options from a `memory_config` value; missing or invalid fields use the defaults
+    pub fn from_memory_config(memory_config: &serde_json::Value) -> Self {
+        memory_config
+            .get("config")
+            .and_then(|config| serde_json::from_value(config.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Options from the saved settings
+    pub fn from_settings() -> Self {
+        crate::settings::load_settings()
+            .map(|settings| Self::from_memory_config(&settings.memory_config))
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("provider", &self.provider)
+            .field("connection_string", &self.connection_string)
+            .field("passphrase", &self.passphrase.as_ref().map(|_| "<redacted>"))
+            .field("pool", &self.pool)
+            .finish()
+    }
 }
 
 /// Supported database providers
@@ -76,8 +238,13 @@ pub enum DatabaseProvider {
 }
 
 /// Main database manager that handles connection and operations
+///
+/// Cloning is cheap: clones share the provider's connection pool.
+#[derive(Clone)]
 pub struct DatabaseManager {
     provider: providers::sqlite::SqliteProvider,
+    db_path: Option<PathBuf>, // None for in-memory databases
+    passphrase: Option<String>, // Kept to reopen the database after a restore
 }
 
 impl DatabaseManager {
@@ -85,24 +252,73 @@ impl DatabaseManager {
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
         match config.provider {
             DatabaseProvider::SQLite => {
-                let provider = providers::sqlite::SqliteProvider::new(&config.connection_string).await?;
-                Ok(Self { provider })
+                let provider = providers::sqlite::SqliteProvider::open(
+                    &config.connection_string,
+                    config.passphrase.as_deref(),
+                    &config.pool,
+                )
+                .await?;
+                Ok(Self {
+                    provider,
+                    db_path: file_path(&config.connection_string),
+                    passphrase: config.passphrase,
+                })
             }
         }
     }
 
-    /// Get the default database path
+    /// Get the default database path, which belongs to the active workspace
     pub fn default_db_path() -> std::path::PathBuf {
-        let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        Path::new(&home_dir)
-            .join(".openconv")
-            .join("db")
-            .join("conv.db")
+        crate::workspaces::active_workspace().db_path()
+    }
+
+    /// Run database migrations, then convert data left in the legacy three-table schema
+    ///
+    /// Every open goes through here, so legacy data is imported whichever way the
+    /// database was opened. Returns the import report when legacy tables were found.
+    pub async fn migrate(&self) -> Result<Option<LegacyImportReport>> {
+        self.provider.migrate().await?;
+        self.provider.import_legacy_tables().await
+    }
+
+    /// Path of the database file, unless the database lives in memory
+    pub fn db_path(&self) -> Option<&Path> {
+        self.db_path.as_deref()
+    }
+
+    /// Passphrase the database was unlocked with, if it is encrypted
+    pub fn passphrase(&self) -> Option<&str> {
+        self.passphrase.as_deref()
     }
 
-    /// Run database migrations
-    pub async fn migrate(&self) -> Result<()> {
-        self.provider.migrate().await
+    /// Write a consistent snapshot of the open database to `dest`
+    pub async fn backup_to(&self, dest: &Path) -> Result<()> {
+        self.provider.backup_to(dest).await
+    }
+
+    /// Run `PRAGMA integrity_check` and `PRAGMA foreign_key_check`
+    pub async fn integrity_check(&self) -> Result<models::IntegrityReport> {
+        self.provider.integrity_check().await
+    }
+
+    /// Rebuild the database file to reclaim space left by deleted data
+    pub async fn vacuum(&self) -> Result<models::VacuumReport> {
+        self.provider.vacuum().await
+    }
+
+    /// Refresh the query planner's statistics
+    pub async fn analyze(&self) -> Result<models::AnalyzeReport> {
+        self.provider.analyze().await
+    }
+
+    /// Copy WAL frames into the main database file
+    pub async fn checkpoint_wal(&self, mode: models::CheckpointMode) -> Result<models::CheckpointReport> {
+        self.provider.checkpoint_wal(mode).await
+    }
+
+    /// Close the connection pool; the manager must not be used afterwards
+    pub async fn close(&self) {
+        self.provider.close().await
     }
 
     /// Get reference to the provider for memory operations
@@ -110,3 +326,18 @@ impl DatabaseManager {
         &self.provider
     }
 }
+
+/// File behind a connection string; `None` for in-memory databases
+fn file_path(connection_string: &str) -> Option<PathBuf> {
+    let path = connection_string
+        .strip_prefix("sqlite://")
+        .unwrap_or(connection_string)
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    if path.is_empty() || path == ":memory:" || path.starts_with(":memory:") {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
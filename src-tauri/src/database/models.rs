@@ -3,9 +3,204 @@
 /// These models represent the structure of data stored in the new two-table design:
 /// - Session: User sessions that act as both persona and conversation container
 /// - Message: Individual messages with embeddings for semantic search
+/// - Persona: Reusable role, goals and model settings that sessions start from
 
+use crate::database::{DatabaseError, Result};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Deserialize a nullable patch field: absent is `None`, `null` is `Some(None)`
+pub(crate) fn double_option<'de, T, D>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Lifecycle state of a session; only open sessions accept message changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum SessionStatus {
+    Open,
+    Closed,
+    Archived,
+}
+
+impl SessionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Open => "open",
+            SessionStatus::Closed => "closed",
+            SessionStatus::Archived => "archived",
+        }
+    }
+
+    /// Whether messages in a session with this status may be added, edited or removed
+    pub fn is_writable(&self) -> bool {
+        *self == SessionStatus::Open
+    }
+
+    /// Allowed transitions: open <-> closed, open/closed -> archived, archived -> open
+    pub fn can_transition_to(&self, next: SessionStatus) -> bool {
+        use SessionStatus::*;
+        *self == next
+            || matches!(
+                (self, next),
+                (Open, Closed) | (Closed, Open) | (Open, Archived) | (Closed, Archived) | (Archived, Open)
+            )
+    }
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Author of a message; serialized as the lowercase strings stored in `message.role`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+    System,
+    Tool,      // Result of a tool/function call
+    Developer, // Instructions for newer models that split system and developer prompts
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::System => "system",
+            MessageRole::Tool => "tool",
+            MessageRole::Developer => "developer",
+        }
+    }
+}
+
+impl fmt::Display for MessageRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One piece of a structured message, stored as JSON in `message.content_parts`
+///
+/// `Message.content` keeps the plain text derived from the parts, so search and
+/// older clients work unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    Image {
+        url: String, // Remote URL, data: URL or attachment path
+        mime_type: Option<String>,
+        alt: Option<String>,
+    },
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: serde_json::Value,
+    },
+    ToolResult {
+        tool_call_id: String,
+        content: String,
+        #[serde(default)]
+        is_error: bool,
+    },
+    Reasoning {
+        text: String,
+    },
+}
+
+impl ContentPart {
+    /// Plain text of a message: text parts and tool results, one per paragraph
+    pub fn plain_text(parts: &[ContentPart]) -> String {
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                ContentPart::ToolResult { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Sampling settings sent with each chat request; `None` fields use the provider default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extras: Option<serde_json::Map<String, serde_json::Value>>, // Provider-specific request fields
+}
+
+impl GenerationParams {
+    /// Request fields the parameters may not override through `extras`
+    const RESERVED: [&'static str; 9] = [
+        "model", "messages", "stream", "temperature", "top_p", "max_tokens", "stop", "seed", "usage",
+    ];
+
+    /// Check values against the ranges accepted by OpenAI-compatible APIs
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(DatabaseError::InvalidGenerationParams(message));
+
+        if let Some(temperature) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return invalid(format!("temperature must be between 0 and 2, got {}", temperature));
+        }
+        if let Some(top_p) = self.top_p.filter(|p| !(*p > 0.0 && *p <= 1.0)) {
+            return invalid(format!("top_p must be greater than 0 and at most 1, got {}", top_p));
+        }
+        if let Some(max_tokens) = self.max_tokens.filter(|m| *m < 1) {
+            return invalid(format!("max_tokens must be positive, got {}", max_tokens));
+        }
+        if self.stop.as_ref().is_some_and(|stop| stop.len() > 4 || stop.iter().any(String::is_empty)) {
+            return invalid("stop accepts at most 4 non-empty sequences".to_string());
+        }
+        if let Some(key) = self
+            .extras
+            .iter()
+            .flat_map(|extras| extras.keys())
+            .find(|key| Self::RESERVED.contains(&key.as_str()))
+        {
+            return invalid(format!("extras cannot set {}", key));
+        }
+        Ok(())
+    }
+
+    /// Names of the standard request fields that are set, for checking model support
+    pub fn requested(&self) -> Vec<&'static str> {
+        [
+            ("temperature", self.temperature.is_some()),
+            ("top_p", self.top_p.is_some()),
+            ("max_tokens", self.max_tokens.is_some()),
+            ("stop", self.stop.is_some()),
+            ("seed", self.seed.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+}
 
 /// Session represents a user session with specific role and goals that also acts as a conversation
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -16,8 +211,31 @@ pub struct Session {
     pub goals: Option<String>,
     pub llm_provider: Option<String>,
     pub model_id: Option<String>,
-    pub status: String, // 'open', 'closed', etc.
+    pub status: SessionStatus,
     pub created_at: i64, // Unix timestamp
+    pub active_leaf_id: Option<i64>, // Last message of the active branch
+    pub forked_from_session_id: Option<i64>, // Source session when this is a fork
+    pub forked_from_message_id: Option<i64>, // Last message copied from the source
+    pub deleted_at: Option<i64>, // Unix timestamp when moved to the trash
+    pub folder_id: Option<i64>,
+    pub pinned: bool,
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    pub include_reasoning: bool, // Send stored reasoning back in prompt context
+    #[sqlx(json)]
+    pub generation_params: GenerationParams,
+    pub persona_id: Option<i64>, // Persona the session was started from
+}
+
+/// Session with the activity figures shown in the session list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: Session,
+    pub message_count: i64,
+    pub last_message_at: Option<i64>, // Unix timestamp in milliseconds
+    pub last_message_preview: Option<String>,
+    pub total_tokens: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,7 +245,146 @@ pub struct CreateSession {
     pub goals: Option<String>,
     pub llm_provider: Option<String>,
     pub model_id: Option<String>,
-    pub status: Option<String>, // Defaults to 'open'
+    pub status: Option<SessionStatus>, // Defaults to open
+    pub persona_id: Option<i64>, // Unset fields are filled from the persona
+}
+
+/// Sort order for session listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSort {
+    #[default]
+    LastActivity,
+    Name,
+    CreatedAt,
+}
+
+/// Filters and sort options for listing sessions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionQuery {
+    pub folder_id: Option<i64>,
+    pub tag: Option<String>,
+    pub pinned: Option<bool>,
+    #[serde(default)]
+    pub include_archived: bool,
+    #[serde(default)]
+    pub sort: SessionSort, // Defaults to last activity
+    pub pinned_first: Option<bool>, // Defaults to true
+}
+
+/// Reusable persona; sessions copy its settings and keep a reference to it
+///
+/// `system_prompt` becomes the session's `role`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Persona {
+    pub id: i64,
+    pub name: String,
+    pub system_prompt: Option<String>,
+    pub goals: Option<String>,
+    pub llm_provider: Option<String>,
+    pub model_id: Option<String>,
+    #[sqlx(json)]
+    pub generation_params: GenerationParams,
+    pub avatar: Option<String>, // Emoji, image URL or attachment path
+    pub created_at: i64, // Unix timestamp
+    pub updated_at: i64, // Unix timestamp
+}
+
+/// New persona; also the YAML layout used for import and export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePersona {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goals: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub generation_params: GenerationParams,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+}
+
+impl From<Persona> for CreatePersona {
+    fn from(persona: Persona) -> Self {
+        Self {
+            name: persona.name,
+            system_prompt: persona.system_prompt,
+            goals: persona.goals,
+            llm_provider: persona.llm_provider,
+            model_id: persona.model_id,
+            generation_params: persona.generation_params,
+            avatar: persona.avatar,
+        }
+    }
+}
+
+/// Partial update for a persona; `None` fields are left unchanged and
+/// `Some(None)` clears a nullable one
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdatePersona {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub goals: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<Option<String>>,
+    pub generation_params: Option<GenerationParams>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<Option<String>>,
+}
+
+/// Conversation read from another app's export, ready to insert
+///
+/// `source` and `external_id` identify it so importing the same archive again
+/// skips conversations that are already present.
+#[derive(Debug)]
+pub struct ImportedConversation {
+    pub source: String, // e.g. 'chatgpt', 'claude'
+    pub external_id: String,
+    pub session: CreateSession,
+    pub created_at: i64, // Unix timestamp
+    pub messages: Vec<ImportedMessage>, // Parents always come before their children
+    pub active_leaf: Option<usize>, // Index into messages; defaults to the last one
+}
+
+#[derive(Debug)]
+pub struct ImportedMessage {
+    pub message: CreateMessage, // session_id and parent_id are assigned on insert
+    pub parent: Option<usize>, // Index of the parent in the conversation's messages
+    pub ts_ms: i64, // Original time in milliseconds
+}
+
+/// Folder for grouping sessions
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Folder {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64, // Unix timestamp
+}
+
+/// Partial update for a session; `None` fields are left unchanged and
+/// `Some(None)` clears a nullable one
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateSession {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub role: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub goals: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<Option<String>>,
+    pub status: Option<SessionStatus>, // Must be a valid transition from the current status
+    pub include_reasoning: Option<bool>,
+    pub generation_params: Option<GenerationParams>, // Replaces the stored parameters
 }
 
 /// Message represents individual messages with optional embeddings
@@ -35,20 +392,103 @@ pub struct CreateSession {
 pub struct Message {
     pub id: i64,
     pub session_id: i64,
-    pub role: String, // 'user', 'assistant', 'system'
+    pub role: MessageRole,
     pub content: String,
     pub ts: i64, // Unix timestamp
+    pub ts_ms: i64, // Unix timestamp in milliseconds
     pub embedding: Option<Vec<u8>>, // Serialized vector embedding
     pub recall_score: Option<f64>,
+    pub parent_id: Option<i64>, // Previous message in the conversation tree
+    pub deleted_at: Option<i64>, // Unix timestamp when moved to the trash
+    pub token_count: Option<i64>,
+    #[sqlx(json(nullable))]
+    pub content_parts: Option<Vec<ContentPart>>, // None for plain-text messages
+    pub reasoning_tokens: Option<i64>, // Share of token_count spent on reasoning/thinking
+    #[sqlx(json(nullable))]
+    pub generation_params: Option<GenerationParams>, // Parameters a reply was generated with
+}
+
+impl Message {
+    /// Structured content, with plain-text messages seen as a single text part
+    pub fn parts(&self) -> Vec<ContentPart> {
+        self.content_parts.clone().unwrap_or_else(|| {
+            vec![ContentPart::Text {
+                text: self.content.clone(),
+            }]
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateMessage {
     pub session_id: i64,
-    pub role: String,
+    pub role: MessageRole,
     pub content: String,
     pub embedding: Option<Vec<u8>>,
     pub recall_score: Option<f64>,
+    pub parent_id: Option<i64>, // Defaults to the session's active leaf; set to branch
+    pub token_count: Option<i64>,
+    pub content_parts: Option<Vec<ContentPart>>, // When set, content is derived from the parts
+    pub reasoning_tokens: Option<i64>,
+    pub generation_params: Option<GenerationParams>,
+}
+
+/// Partial update for a message; `None` fields are left unchanged and
+/// `Some(None)` clears a nullable one
+///
+/// Changing `content` without supplying a new `embedding` clears the stored one.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateMessage {
+    pub content: Option<String>, // Replaces structured content with plain text
+    pub content_parts: Option<Vec<ContentPart>>, // Takes precedence over content
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub recall_score: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "double_option", skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Option<Vec<u8>>>,
+    pub changed_by: Option<String>, // Recorded on the revision when content changes
+}
+
+/// A prior version of a message's content, kept when it is edited or regenerated
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageRevision {
+    pub id: i64,
+    pub message_id: i64,
+    pub content: String,
+    #[sqlx(json(nullable))]
+    pub content_parts: Option<Vec<ContentPart>>,
+    pub changed_by: Option<String>, // e.g. 'user', 'regenerate', 'restore'
+    pub revised_at: i64, // Unix timestamp when the content was replaced
+}
+
+/// Cursor for paging through a session's messages, newest first
+///
+/// `before` and `after` are message ids; at most one of them may be set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageCursor {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// A page of messages ordered by `(ts_ms, id)` descending
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,
+    pub has_more: bool,
+}
+
+/// Trashed sessions, plus trashed messages whose session is not itself trashed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashContents {
+    pub sessions: Vec<Session>,
+    pub messages: Vec<Message>,
+}
+
+/// Rows permanently removed by a trash purge
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub sessions_purged: i64,
+    pub messages_purged: i64,
 }
 
 /// Database statistics for the new architecture
@@ -56,6 +496,91 @@ pub struct CreateMessage {
 pub struct DatabaseStats {
     pub session_count: i64,
     pub message_count: i64,
-    pub database_size_bytes: Option<i64>,
+    pub database_size_bytes: Option<i64>, // Size of the database file; page_count * page_size in memory
     pub vector_index_size: Option<i64>,
+    pub page_size: i64,
+    pub page_count: i64,
+    pub freelist_count: i64, // Unused pages, reclaimed by VACUUM
+    pub reclaimable_bytes: i64,
+    pub wal_size_bytes: Option<i64>, // None when there is no WAL file
+    pub table_row_counts: BTreeMap<String, i64>, // Every row, including trashed ones
+}
+
+/// Result of `PRAGMA integrity_check` and `PRAGMA foreign_key_check`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub problems: Vec<String>, // Empty when ok
+    pub foreign_key_violations: i64,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VacuumReport {
+    pub size_before_bytes: i64,
+    pub size_after_bytes: i64,
+    pub reclaimed_bytes: i64,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzeReport {
+    pub tables_analyzed: i64,
+    pub duration_ms: i64,
+}
+
+/// How aggressively `PRAGMA wal_checkpoint` runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointMode {
+    Passive,
+    Full,
+    Restart,
+    #[default]
+    Truncate, // Also shrinks the WAL file to zero bytes
+}
+
+impl CheckpointMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckpointMode::Passive => "PASSIVE",
+            CheckpointMode::Full => "FULL",
+            CheckpointMode::Restart => "RESTART",
+            CheckpointMode::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointReport {
+    pub mode: CheckpointMode,
+    pub busy: bool, // A reader or writer blocked the checkpoint from finishing
+    pub wal_frames: i64, // -1 when the database is not in WAL mode
+    pub checkpointed_frames: i64,
+    pub wal_size_before_bytes: Option<i64>,
+    pub wal_size_after_bytes: Option<i64>,
+}
+
+/// Row counts moved out of the legacy three-table schema during startup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyImportReport {
+    pub sessions_created: i64,
+    pub long_term_messages: i64,
+    pub short_term_messages: i64,
+    pub knowledge_documents: i64,
+    pub unmapped_fields: Vec<LegacyMemoryFields>,
+}
+
+/// Legacy memory fields the message table has no column for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegacyMemoryFields {
+    pub message_id: i64,
+    pub expires_at: Option<String>, // short_term_memory only
+    pub metadata: Option<String>,
+}
+
+impl LegacyImportReport {
+    pub fn total_rows(&self) -> i64 {
+        self.long_term_messages + self.short_term_messages + self.knowledge_documents
+    }
 }
@@ -0,0 +1,66 @@
+//! YAML import and export of personas
+//!
+//! Each persona is one `<name>.yaml` file in `personas/` under the active workspace, laid out
+//! like `CreatePersona`, so the directory can be shared and checked into a repo. Names that
+//! slugify alike get a numeric suffix, as in `coach.yaml` and `coach-2.yaml`.
+
+use crate::database::export::slugify;
+use crate::database::models::{CreatePersona, Persona};
+use crate::database::{DatabaseError, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory personas are exported to and imported from
+pub fn default_personas_dir() -> PathBuf {
+    crate::workspaces::active_workspace().personas_dir()
+}
+
+/// Write each persona to its own YAML file in `dir`, returning the written paths
+pub fn export_personas(personas: Vec<Persona>, dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    let mut written = Vec::with_capacity(personas.len());
+    let mut stems = HashSet::new();
+    for persona in personas {
+        let slug = slugify(&persona.name);
+        let stem = (1..)
+            .map(|n| if n == 1 { slug.clone() } else { format!("{}-{}", slug, n) })
+            .find(|stem| !stems.contains(stem))
+            .expect("some suffix is free");
+        let path = dir.join(format!("{}.yaml", stem));
+        stems.insert(stem);
+        let yaml = serde_yaml::to_string(&CreatePersona::from(persona)).map_err(|e| DatabaseError::InvalidFile {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        fs::write(&path, yaml)?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// Read every `.yaml` or `.yml` file in `dir`; a missing directory has no personas
+pub fn read_personas(dir: &Path) -> Result<Vec<CreatePersona>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml")))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let yaml = fs::read_to_string(path)?;
+            serde_yaml::from_str(&yaml).map_err(|e| DatabaseError::InvalidFile {
+                path: path.display().to_string(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
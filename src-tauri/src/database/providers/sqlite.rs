@@ -6,18 +6,211 @@
 /// 
 /// Features vector search using sqlite-vss extension with 384-dimensional embeddings.
 
-use crate::database::{models::*, DatabaseError, Result, MemoryRepo};
+use crate::database::{encryption, migrations::{Migration, MigrationRunner}, models::*, DatabaseError, PoolOptions, Result, MemoryRepo};
 use async_trait::async_trait;
-use sqlx::{sqlite::SqlitePool, Row};
-use std::path::Path;
+use sqlx::{sqlite::{Sqlite, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteRow, SqliteSynchronous}, types::Json, ConnectOptions, Connection, QueryBuilder, Row};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
+/// Default page size for cursor-based message pagination
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+// Columns are listed explicitly: `RETURNING *` and `SELECT *` can be sized from a
+// stale schema on pooled connections after a migration adds columns.
+const SESSION_COLUMNS: &str = r#"id, name, role, goals, llm_provider, model_id, status, created_at, active_leaf_id,
+    forked_from_session_id, forked_from_message_id, deleted_at, folder_id, pinned, include_reasoning, generation_params, persona_id,
+    (SELECT json_group_array(tag.name) FROM session_tag JOIN tag ON tag.id = session_tag.tag_id
+     WHERE session_tag.session_id = session.id) AS tags"#;
+const MESSAGE_COLUMNS: &str = "id, session_id, role, content, ts, ts_ms, embedding, recall_score, parent_id, deleted_at, token_count,
+    content_parts, reasoning_tokens, generation_params";
+
+const PERSONA_COLUMNS: &str =
+    "id, name, system_prompt, goals, llm_provider, model_id, generation_params, avatar, created_at, updated_at";
+
+/// Characters of the last message included in session summaries
+const PREVIEW_CHARS: i64 = 160;
+
+/// Walks from a session's active leaf up to the root; binds the session id
+const ACTIVE_PATH_CTE: &str = r#"
+    WITH RECURSIVE active_path(node_id) AS (
+        SELECT active_leaf_id FROM session WHERE id = ? AND deleted_at IS NULL
+        UNION ALL
+        SELECT m.parent_id FROM message m JOIN active_path p ON m.id = p.node_id
+        WHERE m.parent_id IS NOT NULL
+    )
+"#;
+
+/// Walks from a message up to the root, numbering each hop; binds the message id
+const TRANSCRIPT_CTE: &str = r#"
+    WITH RECURSIVE path(node_id, depth) AS (
+        SELECT id, 0 FROM message WHERE id = ?
+        UNION ALL
+        SELECT m.parent_id, p.depth + 1 FROM message m JOIN path p ON m.id = p.node_id
+        WHERE m.parent_id IS NOT NULL
+    )
+"#;
+
+#[derive(Clone)]
 pub struct SqliteProvider {
     pool: SqlitePool,
 }
 
+/// Current Unix time in milliseconds
+fn unix_now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// Apply one migration in its own transaction, checking foreign keys before commit
+async fn apply_migration(conn: &mut SqliteConnection, migration: &dyn Migration) -> Result<()> {
+    let failed = |e: sqlx::Error| DatabaseError::Migration(format!(
+        "Migration {} ({}) failed: {}",
+        migration.version(),
+        migration.description(),
+        e
+    ));
+
+    let mut tx = conn.begin().await?;
+    sqlx::raw_sql(migration.up_sql())
+        .execute(&mut *tx)
+        .await
+        .map_err(failed)?;
+
+    let violations: Vec<SqliteRow> = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(failed)?;
+    if !violations.is_empty() {
+        return Err(DatabaseError::Migration(format!(
+            "Migration {} ({}) left {} foreign key violations",
+            migration.version(),
+            migration.description(),
+            violations.len()
+        )));
+    }
+
+    sqlx::query("INSERT INTO schema_migrations (version, description) VALUES (?, ?)")
+        .bind(migration.version())
+        .bind(migration.description())
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Fail with `SessionReadOnly` unless the session exists and is open
+async fn ensure_session_writable<'e, E>(executor: E, session_id: i64) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let status: Option<SessionStatus> = sqlx::query_scalar("SELECT status FROM session WHERE id = ? AND deleted_at IS NULL")
+        .bind(session_id)
+        .fetch_optional(executor)
+        .await?;
+
+    match status {
+        Some(status) if status.is_writable() => Ok(()),
+        Some(status) => Err(DatabaseError::SessionReadOnly { session_id, status }),
+        None => Err(DatabaseError::Query(format!("Session {} not found", session_id))),
+    }
+}
+
+/// Fail with `SessionReadOnly` if the message belongs to a session that is not open
+async fn ensure_message_writable<'e, E>(executor: E, message_id: i64) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    let owner: Option<(i64, SessionStatus)> = sqlx::query_as(
+        "SELECT session.id, session.status FROM message JOIN session ON session.id = message.session_id WHERE message.id = ?"
+    )
+    .bind(message_id)
+    .fetch_optional(executor)
+    .await?;
+
+    match owner {
+        Some((session_id, status)) if !status.is_writable() => {
+            Err(DatabaseError::SessionReadOnly { session_id, status })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn session_from_row(row: &SqliteRow) -> Session {
+    Session {
+        id: row.get("id"),
+        name: row.get("name"),
+        role: row.get("role"),
+        goals: row.get("goals"),
+        llm_provider: row.get("llm_provider"),
+        model_id: row.get("model_id"),
+        status: row.get("status"),
+        created_at: row.get("created_at"),
+        active_leaf_id: row.get("active_leaf_id"),
+        forked_from_session_id: row.get("forked_from_session_id"),
+        forked_from_message_id: row.get("forked_from_message_id"),
+        deleted_at: row.get("deleted_at"),
+        folder_id: row.get("folder_id"),
+        pinned: row.get("pinned"),
+        tags: serde_json::from_str(row.get("tags")).unwrap_or_default(),
+        include_reasoning: row.get("include_reasoning"),
+        generation_params: serde_json::from_str(row.get("generation_params")).unwrap_or_default(),
+        persona_id: row.get("persona_id"),
+    }
+}
+
+fn session_summary_from_row(row: &SqliteRow) -> SessionSummary {
+    SessionSummary {
+        session: session_from_row(row),
+        message_count: row.get("message_count"),
+        last_message_at: row.get("last_message_at"),
+        last_message_preview: row.get("last_message_preview"),
+        total_tokens: row.get("total_tokens"),
+    }
+}
+
+fn message_from_row(row: &SqliteRow) -> Message {
+    Message {
+        id: row.get("id"),
+        session_id: row.get("session_id"),
+        role: row.get("role"),
+        content: row.get("content"),
+        ts: row.get("ts"),
+        ts_ms: row.get("ts_ms"),
+        embedding: row.get("embedding"),
+        recall_score: row.get("recall_score"),
+        parent_id: row.get("parent_id"),
+        deleted_at: row.get("deleted_at"),
+        token_count: row.get("token_count"),
+        content_parts: row
+            .get::<Option<String>, _>("content_parts")
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        reasoning_tokens: row.get("reasoning_tokens"),
+        generation_params: row
+            .get::<Option<String>, _>("generation_params")
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    }
+}
+
 impl SqliteProvider {
     /// Create a new SQLite provider with the given database path
     pub async fn new(database_url: &str) -> Result<Self> {
+        Self::open(database_url, None, &PoolOptions::default()).await
+    }
+
+    /// Open the database, keying every connection when a passphrase is given
+    ///
+    /// File databases use WAL with `synchronous=NORMAL`, so readers don't block the
+    /// writer, and every connection enforces foreign keys and waits out locks for
+    /// the busy timeout. An encrypted file opened without a passphrase fails with
+    /// `Locked`; a passphrase that doesn't match fails with `WrongPassphrase`.
+    pub async fn open(database_url: &str, passphrase: Option<&str>, pool_options: &PoolOptions) -> Result<Self> {
+        let in_memory = database_url == ":memory:" || database_url.starts_with("sqlite::memory:");
+
         // Handle special cases and ensure parent directory exists for file databases
         let connection_string = if database_url == ":memory:" {
             "sqlite::memory:".to_string()
@@ -28,18 +221,257 @@ impl SqliteProvider {
             if let Some(parent) = Path::new(database_url).parent() {
                 std::fs::create_dir_all(parent)?;
             }
+            if passphrase.is_none() && encryption::is_encrypted(Path::new(database_url))? {
+                return Err(DatabaseError::Locked);
+            }
             format!("sqlite://{}?mode=rwc", database_url)
         };
-        
-        let pool = SqlitePool::connect(&connection_string)
-            .await
-            .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
+        let mut options = SqliteConnectOptions::from_str(&connection_string)
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?
+            .foreign_keys(true)
+            .busy_timeout(Duration::from_millis(pool_options.busy_timeout_ms));
+        if !in_memory {
+            options = options
+                .journal_mode(SqliteJournalMode::Wal)
+                .synchronous(SqliteSynchronous::Normal);
+        }
+        if let Some(passphrase) = passphrase {
+            options = options.pragma("key", encryption::key_pragma(passphrase));
+        }
+
+        // Every connection to `:memory:` opens its own empty database, so an
+        // in-memory pool is a single connection that is never recycled
+        let pool_builder = if in_memory {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .min_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new()
+                .max_connections(pool_options.max_connections.max(1))
+                .min_connections(pool_options.min_connections.min(pool_options.max_connections))
+        };
+
+        // A wrong key shows up as soon as a page is read: while switching to WAL
+        // on connect, or at the first query for databases that are already in WAL
+        let wrong_key = |e: sqlx::Error| {
+            if encryption::is_wrong_key(&e) && passphrase.is_some() {
+                DatabaseError::WrongPassphrase
+            } else {
+                DatabaseError::Connection(e.to_string())
+            }
+        };
+        let pool = pool_builder.connect_with(options).await.map_err(wrong_key)?;
+        if let Err(e) = sqlx::query("SELECT count(*) FROM sqlite_master").fetch_one(&pool).await {
+            pool.close().await;
+            return Err(wrong_key(e));
+        }
 
         Ok(Self { pool })
     }
 
+    /// Write a consistent snapshot of the database to `dest` while it stays in use
+    ///
+    /// `VACUUM INTO` reads inside a single transaction, so concurrent writers never
+    /// leave the copy half-updated. The snapshot is written next to `dest` first and
+    /// renamed into place, so an interrupted backup never looks complete.
+    pub async fn backup_to(&self, dest: &Path) -> Result<()> {
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let partial = dest.with_extension("partial");
+        if partial.exists() {
+            std::fs::remove_file(&partial)?;
+        }
+
+        sqlx::query("VACUUM INTO ?")
+            .bind(partial.to_string_lossy().as_ref())
+            .execute(&self.pool)
+            .await?;
+        std::fs::rename(&partial, dest)?;
+
+        Ok(())
+    }
+
+    /// Check that the database file at `path` is intact and holds OpenConverse data
+    pub async fn verify_file(path: &Path, passphrase: Option<&str>) -> Result<()> {
+        let invalid = |message: String| DatabaseError::InvalidFile {
+            path: path.display().to_string(),
+            message,
+        };
+        if !path.is_file() {
+            return Err(invalid("file not found".to_string()));
+        }
+
+        let mut options = SqliteConnectOptions::new().filename(path).read_only(true);
+        if let Some(passphrase) = passphrase {
+            options = options.pragma("key", encryption::key_pragma(passphrase));
+        }
+        let mut conn = options.connect().await.map_err(|e| invalid(e.to_string()))?;
+
+        let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_all(&mut conn)
+            .await
+            .map_err(|e| invalid(e.to_string()))?;
+        if problems != ["ok"] {
+            return Err(invalid(format!("integrity check failed: {}", problems.join("; "))));
+        }
+
+        let has_sessions: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'session')"
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        if !has_sessions {
+            return Err(invalid("not an OpenConverse database".to_string()));
+        }
+
+        conn.close().await?;
+        Ok(())
+    }
+
+    /// Close every pooled connection so the database file can be replaced
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    // === Maintenance ===
+
+    /// Path of the main database file; `None` for in-memory databases
+    async fn database_file(&self) -> Result<Option<PathBuf>> {
+        let databases: Vec<(i64, String, String)> = sqlx::query_as("PRAGMA database_list")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(databases
+            .into_iter()
+            .find(|(_, name, _)| name == "main")
+            .map(|(_, _, file)| file)
+            .filter(|file| !file.is_empty())
+            .map(PathBuf::from))
+    }
+
+    async fn pragma_value(&self, pragma: &str) -> Result<i64> {
+        Ok(sqlx::query_scalar(&format!("PRAGMA {}", pragma))
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    /// Size of the main file and of its WAL file, if there is one
+    async fn file_sizes(&self) -> Result<(i64, Option<i64>)> {
+        let file_size = |path: &Path| std::fs::metadata(path).ok().map(|metadata| metadata.len() as i64);
+
+        match self.database_file().await? {
+            Some(path) => {
+                let wal = PathBuf::from(format!("{}-wal", path.display()));
+                Ok((file_size(&path).unwrap_or(0), file_size(&wal)))
+            }
+            None => {
+                let page_size = self.pragma_value("page_size").await?;
+                let page_count = self.pragma_value("page_count").await?;
+                Ok((page_size * page_count, None))
+            }
+        }
+    }
+
+    /// Run `PRAGMA integrity_check` and `PRAGMA foreign_key_check`
+    pub async fn integrity_check(&self) -> Result<IntegrityReport> {
+        let started = Instant::now();
+        let mut problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await?;
+        problems.retain(|problem| problem != "ok");
+
+        let violations: Vec<SqliteRow> = sqlx::query("PRAGMA foreign_key_check")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(IntegrityReport {
+            ok: problems.is_empty() && violations.is_empty(),
+            problems,
+            foreign_key_violations: violations.len() as i64,
+            duration_ms: started.elapsed().as_millis() as i64,
+        })
+    }
+
+    /// Rebuild the database file, returning free pages to the file system
+    ///
+    /// Purged sessions leave their pages on the freelist; only `VACUUM` shrinks
+    /// the file. It needs free disk space of up to twice the database size.
+    pub async fn vacuum(&self) -> Result<VacuumReport> {
+        let started = Instant::now();
+        let (size_before, _) = self.file_sizes().await?;
+
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        // In WAL mode the rewritten pages only reach the main file at a checkpoint
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&self.pool).await?;
+
+        let (size_after, _) = self.file_sizes().await?;
+        Ok(VacuumReport {
+            size_before_bytes: size_before,
+            size_after_bytes: size_after,
+            reclaimed_bytes: (size_before - size_after).max(0),
+            duration_ms: started.elapsed().as_millis() as i64,
+        })
+    }
+
+    /// Refresh the query planner's statistics
+    pub async fn analyze(&self) -> Result<AnalyzeReport> {
+        let started = Instant::now();
+        sqlx::query("ANALYZE").execute(&self.pool).await?;
+
+        let tables_analyzed: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT tbl) FROM sqlite_stat1")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(AnalyzeReport {
+            tables_analyzed,
+            duration_ms: started.elapsed().as_millis() as i64,
+        })
+    }
+
+    /// Copy WAL frames into the main database file
+    pub async fn checkpoint_wal(&self, mode: CheckpointMode) -> Result<CheckpointReport> {
+        let (_, wal_before) = self.file_sizes().await?;
+
+        let (busy, wal_frames, checkpointed_frames): (i64, i64, i64) =
+            sqlx::query_as(&format!("PRAGMA wal_checkpoint({})", mode.as_str()))
+                .fetch_one(&self.pool)
+                .await?;
+
+        let (_, wal_after) = self.file_sizes().await?;
+        Ok(CheckpointReport {
+            mode,
+            busy: busy != 0,
+            wal_frames,
+            checkpointed_frames,
+            wal_size_before_bytes: wal_before,
+            wal_size_after_bytes: wal_after,
+        })
+    }
+
     /// Run database migrations to create the new schema
     pub async fn migrate(&self) -> Result<()> {
+        self.create_base_schema().await?;
+        self.apply_versioned_migrations(None).await
+    }
+
+    /// Bring the schema up to `version` only, so later migrations can be tested against older data
+    #[cfg(test)]
+    pub(crate) async fn migrate_to(&self, version: i32) -> Result<()> {
+        self.create_base_schema().await?;
+        self.apply_versioned_migrations(Some(version)).await
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Tables that predate versioned migrations
+    async fn create_base_schema(&self) -> Result<()> {
         // Create session table with new fields
         sqlx::query(
             r#"
@@ -93,8 +525,279 @@ impl SqliteProvider {
             .execute(&self.pool)
             .await?;
 
+        // Create knowledge table for standalone documents (formerly vector_db)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS knowledge (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                document_id TEXT NOT NULL UNIQUE,
+                collection_name TEXT NOT NULL DEFAULT 'default',
+                content TEXT NOT NULL,
+                embedding BLOB,
+                metadata TEXT,
+                created_at INTEGER NOT NULL DEFAULT (unixepoch())
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_knowledge_collection ON knowledge(collection_name)")
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
+
+    /// Apply pending versioned migrations up to `target`, tracking them in `schema_migrations`
+    async fn apply_versioned_migrations(&self, target: Option<i32>) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let applied: Vec<i32> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+            .fetch_all(&self.pool)
+            .await?;
+
+        // Table rebuilds copy rows into a new table and drop the old one, which must
+        // not fire ON DELETE actions. The pragma is ignored inside a transaction, so it
+        // is switched off on one connection around the whole run.
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+
+        let mut result = Ok(());
+        for migration in MigrationRunner::new().get_migrations() {
+            if applied.contains(&migration.version()) || target.is_some_and(|target| migration.version() > target) {
+                continue;
+            }
+
+            result = apply_migration(&mut conn, migration.as_ref()).await;
+            if result.is_err() {
+                break;
+            }
+        }
+
+        sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+        result
+    }
+
+    /// List non-trashed sessions with their activity figures, filtered and sorted
+    async fn query_sessions(&self, query: SessionQuery) -> Result<Vec<SqliteRow>> {
+        // Activity figures come from one grouped pass over the covering partial index;
+        // without ANALYZE stats the planner would otherwise pick idx_message_deleted_at
+        // and sort in a temp b-tree
+        let mut builder = QueryBuilder::<Sqlite>::new(format!(
+            r#"
+            SELECT {},
+                COALESCE(activity.message_count, 0) AS message_count,
+                activity.last_message_at,
+                COALESCE(activity.total_tokens, 0) AS total_tokens,
+                (SELECT substr(latest.content, 1, {}) FROM message latest
+                 WHERE latest.session_id = session.id AND latest.deleted_at IS NULL
+                 ORDER BY latest.ts_ms DESC, latest.id DESC LIMIT 1) AS last_message_preview
+            FROM session
+            LEFT JOIN (
+                SELECT session_id,
+                    COUNT(*) AS message_count,
+                    MAX(ts_ms) AS last_message_at,
+                    SUM(token_count) AS total_tokens
+                FROM message INDEXED BY idx_message_session_activity
+                WHERE deleted_at IS NULL
+                GROUP BY session_id
+            ) activity ON activity.session_id = session.id
+            WHERE session.deleted_at IS NULL
+            "#,
+            SESSION_COLUMNS, PREVIEW_CHARS
+        ));
+
+        if !query.include_archived {
+            builder.push(" AND status <> 'archived'");
+        }
+        if let Some(folder_id) = query.folder_id {
+            builder.push(" AND folder_id = ").push_bind(folder_id);
+        }
+        if let Some(tag) = query.tag {
+            builder
+                .push(" AND id IN (SELECT session_tag.session_id FROM session_tag JOIN tag ON tag.id = session_tag.tag_id WHERE tag.name = ")
+                .push_bind(tag)
+                .push(")");
+        }
+        if let Some(pinned) = query.pinned {
+            builder.push(" AND pinned = ").push_bind(pinned);
+        }
+
+        builder.push(" ORDER BY ");
+        if query.pinned_first.unwrap_or(true) {
+            builder.push("pinned DESC, ");
+        }
+        builder.push(match query.sort {
+            SessionSort::LastActivity => "COALESCE(activity.last_message_at, created_at * 1000) DESC",
+            SessionSort::Name => "name COLLATE NOCASE ASC",
+            SessionSort::CreatedAt => "created_at DESC",
+        });
+        builder.push(", id DESC");
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+
+        Ok(rows)
+    }
+
+    /// Move rows from the legacy `long_term_memory`, `short_term_memory` and
+    /// `vector_db` tables into the current schema, then drop the legacy tables.
+    ///
+    /// Memory rows become system messages in one imported session per table,
+    /// and `vector_db` documents are copied into `knowledge` with their embeddings.
+    /// Returns `None` when no legacy tables are present.
+    pub async fn import_legacy_tables(&self) -> Result<Option<LegacyImportReport>> {
+        let legacy_tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ('long_term_memory', 'short_term_memory', 'vector_db')"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        if legacy_tables.is_empty() {
+            return Ok(None);
+        }
+
+        let mut report = LegacyImportReport::default();
+        let mut tx = self.pool.begin().await?;
+
+        for (table, session_name) in [
+            ("long_term_memory", "Imported long-term memory"),
+            ("short_term_memory", "Imported short-term memory"),
+        ] {
+            if !legacy_tables.iter().any(|t| t == table) {
+                continue;
+            }
+
+            let expected: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(&mut *tx)
+                .await?;
+            if expected == 0 {
+                continue;
+            }
+
+            let session_id: i64 = sqlx::query_scalar(&format!(
+                "INSERT INTO session (name, status, created_at) SELECT ?, 'closed', COALESCE(unixepoch(MIN(created_at)), unixepoch()) FROM {} RETURNING id",
+                table
+            ))
+            .bind(session_name)
+            .fetch_one(&mut *tx)
+            .await?;
+            report.sessions_created += 1;
+
+            let moved = sqlx::query(&format!(
+                "INSERT INTO message (session_id, role, content, ts, ts_ms) SELECT ?, 'system', content, COALESCE(unixepoch(created_at), unixepoch()), COALESCE(unixepoch(created_at), unixepoch()) * 1000 FROM {} ORDER BY id",
+                table
+            ))
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+            if moved != expected {
+                return Err(DatabaseError::Migration(format!(
+                    "Legacy import of {} moved {} of {} rows",
+                    table, moved, expected
+                )));
+            }
+
+            // Imported memories form a single linear branch
+            sqlx::query(
+                r#"
+                UPDATE message SET parent_id = (
+                    SELECT prev.id FROM message prev
+                    WHERE prev.session_id = message.session_id AND prev.id < message.id
+                    ORDER BY prev.id DESC
+                    LIMIT 1
+                )
+                WHERE session_id = ?
+                "#,
+            )
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query("UPDATE session SET active_leaf_id = (SELECT MAX(id) FROM message WHERE session_id = ?) WHERE id = ?")
+                .bind(session_id)
+                .bind(session_id)
+                .execute(&mut *tx)
+                .await?;
+
+            // Expiry and metadata have no column on messages; report them rather than drop them silently
+            let expires_at = if table == "short_term_memory" { "expires_at" } else { "NULL" };
+            let legacy_fields: Vec<(Option<String>, Option<String>)> = sqlx::query_as(&format!(
+                "SELECT CAST({} AS TEXT), metadata FROM {} ORDER BY id",
+                expires_at, table
+            ))
+            .fetch_all(&mut *tx)
+            .await?;
+            let message_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM message WHERE session_id = ? ORDER BY id")
+                .bind(session_id)
+                .fetch_all(&mut *tx)
+                .await?;
+            report.unmapped_fields.extend(
+                message_ids
+                    .into_iter()
+                    .zip(legacy_fields)
+                    .filter(|(_, (expires_at, metadata))| expires_at.is_some() || metadata.is_some())
+                    .map(|(message_id, (expires_at, metadata))| LegacyMemoryFields {
+                        message_id,
+                        expires_at,
+                        metadata,
+                    }),
+            );
+
+            if table == "long_term_memory" {
+                report.long_term_messages = moved;
+            } else {
+                report.short_term_messages = moved;
+            }
+        }
+
+        if legacy_tables.iter().any(|t| t == "vector_db") {
+            let expected: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM vector_db")
+                .fetch_one(&mut *tx)
+                .await?;
+
+            let moved = sqlx::query(
+                r#"
+                INSERT INTO knowledge (document_id, collection_name, content, embedding, metadata, created_at)
+                SELECT document_id, collection_name, content, embedding, metadata, COALESCE(unixepoch(created_at), unixepoch())
+                FROM vector_db ORDER BY id
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+            if moved != expected {
+                return Err(DatabaseError::Migration(format!(
+                    "Legacy import of vector_db moved {} of {} rows",
+                    moved, expected
+                )));
+            }
+            report.knowledge_documents = moved;
+        }
+
+        for table in &legacy_tables {
+            sqlx::query(&format!("DROP TABLE {}", table))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(report))
+    }
 }
 
 #[async_trait]
@@ -107,78 +810,635 @@ impl MemoryRepo for SqliteProvider {
             .unwrap()
             .as_secs() as i64;
 
-        let status = session.status.unwrap_or_else(|| "open".to_string());
+        let status = session.status.unwrap_or(SessionStatus::Open);
+
+        // Fields the caller leaves unset are copied from the persona
+        let persona = match session.persona_id {
+            Some(persona_id) => Some(self.get_persona(persona_id).await?),
+            None => None,
+        };
+        let from_persona = |field: fn(&Persona) -> Option<String>| persona.as_ref().and_then(field);
+        let role = session.role.or_else(|| from_persona(|p| p.system_prompt.clone()));
+        let goals = session.goals.or_else(|| from_persona(|p| p.goals.clone()));
+        let llm_provider = session.llm_provider.or_else(|| from_persona(|p| p.llm_provider.clone()));
+        let model_id = session.model_id.or_else(|| from_persona(|p| p.model_id.clone()));
+        let generation_params = persona
+            .as_ref()
+            .map(|p| p.generation_params.clone())
+            .unwrap_or_default();
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO session (name, role, goals, llm_provider, model_id, status, created_at, persona_id, generation_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(&session.name)
+        .bind(&role)
+        .bind(&goals)
+        .bind(&llm_provider)
+        .bind(&model_id)
+        .bind(status)
+        .bind(now)
+        .bind(session.persona_id)
+        .bind(Json(&generation_params))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session_from_row(&result))
+    }
+
+    async fn get_sessions(&self, query: SessionQuery) -> Result<Vec<Session>> {
+        let rows = self.query_sessions(query).await?;
+
+        let sessions = rows.iter().map(session_from_row).collect();
+
+        Ok(sessions)
+    }
+
+    async fn session_summaries(&self, query: SessionQuery) -> Result<Vec<SessionSummary>> {
+        let rows = self.query_sessions(query).await?;
+
+        Ok(rows.iter().map(session_summary_from_row).collect())
+    }
+
+    async fn get_session_by_id(&self, session_id: i64) -> Result<Session> {
+        let row = sqlx::query(&format!("SELECT {} FROM session WHERE id = ? AND deleted_at IS NULL", SESSION_COLUMNS))
+            .bind(session_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn update_session(&self, session_id: i64, update: UpdateSession) -> Result<Session> {
+        if let Some(params) = &update.generation_params {
+            params.validate()?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(next) = update.status {
+            let current: SessionStatus = sqlx::query_scalar("SELECT status FROM session WHERE id = ? AND deleted_at IS NULL")
+                .bind(session_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            if !current.can_transition_to(next) {
+                return Err(DatabaseError::InvalidStatusTransition { from: current, to: next });
+            }
+        }
+
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE session SET
+                name = COALESCE(?, name),
+                role = CASE WHEN ? THEN ? ELSE role END,
+                goals = CASE WHEN ? THEN ? ELSE goals END,
+                llm_provider = CASE WHEN ? THEN ? ELSE llm_provider END,
+                model_id = CASE WHEN ? THEN ? ELSE model_id END,
+                status = COALESCE(?, status),
+                include_reasoning = COALESCE(?, include_reasoning),
+                generation_params = COALESCE(?, generation_params)
+            WHERE id = ? AND deleted_at IS NULL
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(&update.name)
+        .bind(update.role.is_some())
+        .bind(update.role.as_ref().and_then(Option::as_deref))
+        .bind(update.goals.is_some())
+        .bind(update.goals.as_ref().and_then(Option::as_deref))
+        .bind(update.llm_provider.is_some())
+        .bind(update.llm_provider.as_ref().and_then(Option::as_deref))
+        .bind(update.model_id.is_some())
+        .bind(update.model_id.as_ref().and_then(Option::as_deref))
+        .bind(update.status)
+        .bind(update.include_reasoning)
+        .bind(update.generation_params.as_ref().map(Json))
+        .bind(session_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(session_from_row(&row))
+    }
 
-        let result = sqlx::query(
-            "INSERT INTO session (name, role, goals, llm_provider, model_id, status, created_at) VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *"
+    async fn fork_session(&self, session_id: i64, message_id: i64) -> Result<Session> {
+        let mut tx = self.pool.begin().await?;
+
+        let source = sqlx::query(&format!("SELECT {} FROM session WHERE id = ? AND deleted_at IS NULL", SESSION_COLUMNS))
+            .bind(session_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let source = session_from_row(&source);
+
+        // The transcript skips trashed messages, so a trashed fork point has to be
+        // rejected up front rather than silently replaced by its parent
+        let fork_point: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM message WHERE id = ? AND session_id = ? AND deleted_at IS NULL"
+        )
+        .bind(message_id)
+        .bind(session_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if fork_point.is_none() {
+            return Err(DatabaseError::Query(format!(
+                "Message {} not found in session {}",
+                message_id, session_id
+            )));
+        }
+
+        let transcript = sqlx::query(&format!(
+            "{} SELECT {} FROM message JOIN path ON id = node_id WHERE deleted_at IS NULL ORDER BY depth DESC",
+            TRANSCRIPT_CTE, MESSAGE_COLUMNS
+        ))
+        .bind(message_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let transcript: Vec<Message> = transcript.iter().map(message_from_row).collect();
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO session (name, role, goals, llm_provider, model_id, status, created_at, forked_from_session_id, forked_from_message_id, include_reasoning, generation_params, persona_id)
+            VALUES (?, ?, ?, ?, ?, 'open', ?, ?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(format!("{} (fork)", source.name))
+        .bind(&source.role)
+        .bind(&source.goals)
+        .bind(&source.llm_provider)
+        .bind(&source.model_id)
+        .bind(unix_now_ms() / 1000)
+        .bind(source.id)
+        .bind(message_id)
+        .bind(source.include_reasoning)
+        .bind(Json(&source.generation_params))
+        .bind(source.persona_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let fork_id: i64 = row.get("id");
+
+        // Copy the transcript as a single linear branch, keeping original timestamps
+        let mut parent_id: Option<i64> = None;
+        for message in &transcript {
+            let copied_id: i64 = sqlx::query_scalar(
+                "INSERT INTO message (session_id, role, content, ts, ts_ms, embedding, recall_score, parent_id, token_count, content_parts, reasoning_tokens, generation_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
+            )
+            .bind(fork_id)
+            .bind(message.role)
+            .bind(&message.content)
+            .bind(message.ts)
+            .bind(message.ts_ms)
+            .bind(&message.embedding)
+            .bind(message.recall_score)
+            .bind(parent_id)
+            .bind(message.token_count)
+            .bind(message.content_parts.as_ref().map(Json))
+            .bind(message.reasoning_tokens)
+            .bind(message.generation_params.as_ref().map(Json))
+            .fetch_one(&mut *tx)
+            .await?;
+            parent_id = Some(copied_id);
+        }
+
+        let row = sqlx::query(&format!("UPDATE session SET active_leaf_id = ? WHERE id = ? RETURNING {}", SESSION_COLUMNS))
+            .bind(parent_id)
+            .bind(fork_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn delete_session(&self, session_id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE session SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(unix_now_ms() / 1000)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn restore_session(&self, session_id: i64) -> Result<Session> {
+        let row = sqlx::query(&format!(
+            "UPDATE session SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn import_conversation(&self, conversation: ImportedConversation) -> Result<Option<Session>> {
+        let mut tx = self.pool.begin().await?;
+
+        // Trashed sessions count as present so a re-import doesn't bring them back
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM session WHERE import_source = ? AND import_id = ?")
+            .bind(&conversation.source)
+            .bind(&conversation.external_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if existing.is_some() {
+            return Ok(None);
+        }
+
+        let session = conversation.session;
+        let session_id: i64 = sqlx::query_scalar(
+            "INSERT INTO session (name, role, goals, llm_provider, model_id, status, created_at, import_source, import_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
         )
         .bind(&session.name)
         .bind(&session.role)
         .bind(&session.goals)
         .bind(&session.llm_provider)
         .bind(&session.model_id)
-        .bind(&status)
-        .bind(now)
+        .bind(session.status.unwrap_or(SessionStatus::Open))
+        .bind(conversation.created_at)
+        .bind(&conversation.source)
+        .bind(&conversation.external_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut message_ids: Vec<i64> = Vec::with_capacity(conversation.messages.len());
+        for imported in conversation.messages {
+            let parent_id = match imported.parent {
+                Some(index) => Some(*message_ids.get(index).ok_or_else(|| {
+                    DatabaseError::Query(format!("Imported message parent {} comes after its child", index))
+                })?),
+                None => None,
+            };
+
+            let message = imported.message;
+            let content = match &message.content_parts {
+                Some(parts) => ContentPart::plain_text(parts),
+                None => message.content.clone(),
+            };
+
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO message (session_id, role, content, ts, ts_ms, embedding, recall_score, parent_id, token_count, content_parts, reasoning_tokens, generation_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
+            )
+            .bind(session_id)
+            .bind(message.role)
+            .bind(&content)
+            .bind(imported.ts_ms / 1000)
+            .bind(imported.ts_ms)
+            .bind(&message.embedding)
+            .bind(message.recall_score)
+            .bind(parent_id)
+            .bind(message.token_count)
+            .bind(message.content_parts.as_ref().map(Json))
+            .bind(message.reasoning_tokens)
+            .bind(message.generation_params.as_ref().map(Json))
+            .fetch_one(&mut *tx)
+            .await?;
+            message_ids.push(id);
+        }
+
+        let active_leaf_id = conversation
+            .active_leaf
+            .and_then(|index| message_ids.get(index).copied())
+            .or_else(|| message_ids.last().copied());
+
+        let row = sqlx::query(&format!(
+            "UPDATE session SET active_leaf_id = ? WHERE id = ? RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(active_leaf_id)
+        .bind(session_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(session_from_row(&row)))
+    }
+
+    // === Session Organisation Operations ===
+
+    async fn create_folder(&self, name: String) -> Result<Folder> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DatabaseError::Query("Folder name must not be empty".to_string()));
+        }
+
+        let folder = sqlx::query_as::<_, Folder>(
+            "INSERT INTO folder (name, created_at) VALUES (?, ?) RETURNING id, name, created_at"
+        )
+        .bind(name)
+        .bind(unix_now_ms() / 1000)
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(Session {
-            id: result.get("id"),
-            name: result.get("name"),
-            role: result.get("role"),
-            goals: result.get("goals"),
-            llm_provider: result.get("llm_provider"),
-            model_id: result.get("model_id"),
-            status: result.get("status"),
-            created_at: result.get("created_at"),
-        })
+        Ok(folder)
     }
 
-    async fn get_sessions(&self) -> Result<Vec<Session>> {
-        let rows = sqlx::query("SELECT * FROM session ORDER BY created_at DESC")
+    async fn list_folders(&self) -> Result<Vec<Folder>> {
+        let folders = sqlx::query_as::<_, Folder>("SELECT id, name, created_at FROM folder ORDER BY name COLLATE NOCASE")
             .fetch_all(&self.pool)
             .await?;
-        
-        let sessions = rows
-            .into_iter()
-            .map(|row| Session {
-                id: row.get("id"),
-                name: row.get("name"),
-                role: row.get("role"),
-                goals: row.get("goals"),
-                llm_provider: row.get("llm_provider"),
-                model_id: row.get("model_id"),
-                status: row.get("status"),
-                created_at: row.get("created_at"),
-            })
-            .collect();
 
-        Ok(sessions)
+        Ok(folders)
     }
 
-    async fn get_session_by_id(&self, session_id: i64) -> Result<Session> {
-        let row = sqlx::query("SELECT * FROM session WHERE id = ?")
+    async fn delete_folder(&self, folder_id: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Sessions in the folder are kept and become unfiled
+        sqlx::query("UPDATE session SET folder_id = NULL WHERE folder_id = ?")
+            .bind(folder_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM folder WHERE id = ?")
+            .bind(folder_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_session_folder(&self, session_id: i64, folder_id: Option<i64>) -> Result<Session> {
+        if let Some(folder_id) = folder_id {
+            let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM folder WHERE id = ?")
+                .bind(folder_id)
+                .fetch_optional(&self.pool)
+                .await?;
+            if exists.is_none() {
+                return Err(DatabaseError::Query(format!("Folder {} not found", folder_id)));
+            }
+        }
+
+        let row = sqlx::query(&format!(
+            "UPDATE session SET folder_id = ? WHERE id = ? AND deleted_at IS NULL RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(folder_id)
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn set_session_tags(&self, session_id: i64, tags: Vec<String>) -> Result<Session> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM session_tag WHERE session_id = ?")
             .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            sqlx::query("INSERT INTO tag (name) VALUES (?) ON CONFLICT (name) DO NOTHING")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("INSERT OR IGNORE INTO session_tag (session_id, tag_id) SELECT ?, id FROM tag WHERE name = ?")
+                .bind(session_id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        // Drop tags no session uses any more
+        sqlx::query("DELETE FROM tag WHERE id NOT IN (SELECT tag_id FROM session_tag)")
+            .execute(&mut *tx)
+            .await?;
+
+        let row = sqlx::query(&format!("SELECT {} FROM session WHERE id = ? AND deleted_at IS NULL", SESSION_COLUMNS))
+            .bind(session_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn list_tags(&self) -> Result<Vec<String>> {
+        let tags = sqlx::query_scalar("SELECT name FROM tag ORDER BY name COLLATE NOCASE")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tags)
+    }
+
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<Session> {
+        let row = sqlx::query(&format!(
+            "UPDATE session SET pinned = ? WHERE id = ? AND deleted_at IS NULL RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(pinned)
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn set_session_status(&self, session_id: i64, status: SessionStatus) -> Result<Session> {
+        self.update_session(
+            session_id,
+            UpdateSession {
+                status: Some(status),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    // === Persona Operations ===
+
+    async fn create_persona(&self, persona: CreatePersona) -> Result<Persona> {
+        let name = persona.name.trim();
+        if name.is_empty() {
+            return Err(DatabaseError::Query("Persona name must not be empty".to_string()));
+        }
+        persona.generation_params.validate()?;
+
+        let now = unix_now_ms() / 1000;
+        let created = sqlx::query_as::<_, Persona>(&format!(
+            r#"
+            INSERT INTO persona (name, system_prompt, goals, llm_provider, model_id, generation_params, avatar, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            PERSONA_COLUMNS
+        ))
+        .bind(name)
+        .bind(&persona.system_prompt)
+        .bind(&persona.goals)
+        .bind(&persona.llm_provider)
+        .bind(&persona.model_id)
+        .bind(Json(&persona.generation_params))
+        .bind(&persona.avatar)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    async fn upsert_persona(&self, persona: CreatePersona) -> Result<Persona> {
+        let name = persona.name.trim();
+        if name.is_empty() {
+            return Err(DatabaseError::Query("Persona name must not be empty".to_string()));
+        }
+        persona.generation_params.validate()?;
+
+        // Personas are matched by name, so re-importing a file updates it in place
+        let now = unix_now_ms() / 1000;
+        let saved = sqlx::query_as::<_, Persona>(&format!(
+            r#"
+            INSERT INTO persona (name, system_prompt, goals, llm_provider, model_id, generation_params, avatar, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                system_prompt = excluded.system_prompt,
+                goals = excluded.goals,
+                llm_provider = excluded.llm_provider,
+                model_id = excluded.model_id,
+                generation_params = excluded.generation_params,
+                avatar = excluded.avatar,
+                updated_at = excluded.updated_at
+            RETURNING {}
+            "#,
+            PERSONA_COLUMNS
+        ))
+        .bind(name)
+        .bind(&persona.system_prompt)
+        .bind(&persona.goals)
+        .bind(&persona.llm_provider)
+        .bind(&persona.model_id)
+        .bind(Json(&persona.generation_params))
+        .bind(&persona.avatar)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(saved)
+    }
+
+    async fn list_personas(&self) -> Result<Vec<Persona>> {
+        let personas = sqlx::query_as::<_, Persona>(&format!(
+            "SELECT {} FROM persona ORDER BY name COLLATE NOCASE",
+            PERSONA_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(personas)
+    }
+
+    async fn get_persona(&self, persona_id: i64) -> Result<Persona> {
+        let persona = sqlx::query_as::<_, Persona>(&format!("SELECT {} FROM persona WHERE id = ?", PERSONA_COLUMNS))
+            .bind(persona_id)
             .fetch_one(&self.pool)
             .await?;
 
-        Ok(Session {
-            id: row.get("id"),
-            name: row.get("name"),
-            role: row.get("role"),
-            goals: row.get("goals"),
-            llm_provider: row.get("llm_provider"),
-            model_id: row.get("model_id"),
-            status: row.get("status"),
-            created_at: row.get("created_at"),
-        })
+        Ok(persona)
     }
 
-    async fn delete_session(&self, session_id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM session WHERE id = ?")
-            .bind(session_id)
-            .execute(&self.pool)
+    async fn update_persona(&self, persona_id: i64, update: UpdatePersona, apply_to_sessions: bool) -> Result<Persona> {
+        if update.name.as_ref().is_some_and(|name| name.trim().is_empty()) {
+            return Err(DatabaseError::Query("Persona name must not be empty".to_string()));
+        }
+        if let Some(params) = &update.generation_params {
+            params.validate()?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let persona = sqlx::query_as::<_, Persona>(&format!(
+            r#"
+            UPDATE persona SET
+                name = COALESCE(?, name),
+                system_prompt = CASE WHEN ? THEN ? ELSE system_prompt END,
+                goals = CASE WHEN ? THEN ? ELSE goals END,
+                llm_provider = CASE WHEN ? THEN ? ELSE llm_provider END,
+                model_id = CASE WHEN ? THEN ? ELSE model_id END,
+                generation_params = COALESCE(?, generation_params),
+                avatar = CASE WHEN ? THEN ? ELSE avatar END,
+                updated_at = ?
+            WHERE id = ?
+            RETURNING {}
+            "#,
+            PERSONA_COLUMNS
+        ))
+        .bind(update.name.as_deref().map(str::trim))
+        .bind(update.system_prompt.is_some())
+        .bind(update.system_prompt.as_ref().and_then(Option::as_deref))
+        .bind(update.goals.is_some())
+        .bind(update.goals.as_ref().and_then(Option::as_deref))
+        .bind(update.llm_provider.is_some())
+        .bind(update.llm_provider.as_ref().and_then(Option::as_deref))
+        .bind(update.model_id.is_some())
+        .bind(update.model_id.as_ref().and_then(Option::as_deref))
+        .bind(update.generation_params.as_ref().map(Json))
+        .bind(update.avatar.is_some())
+        .bind(update.avatar.as_ref().and_then(Option::as_deref))
+        .bind(unix_now_ms() / 1000)
+        .bind(persona_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // Only the fields changed by this edit are copied, so other per-session tweaks survive
+        if apply_to_sessions {
+            sqlx::query(
+                r#"
+                UPDATE session SET
+                    role = CASE WHEN ? THEN ? ELSE role END,
+                    goals = CASE WHEN ? THEN ? ELSE goals END,
+                    llm_provider = CASE WHEN ? THEN ? ELSE llm_provider END,
+                    model_id = CASE WHEN ? THEN ? ELSE model_id END,
+                    generation_params = COALESCE(?, generation_params)
+                WHERE persona_id = ? AND deleted_at IS NULL
+                "#,
+            )
+            .bind(update.system_prompt.is_some())
+            .bind(update.system_prompt.as_ref().and_then(Option::as_deref))
+            .bind(update.goals.is_some())
+            .bind(update.goals.as_ref().and_then(Option::as_deref))
+            .bind(update.llm_provider.is_some())
+            .bind(update.llm_provider.as_ref().and_then(Option::as_deref))
+            .bind(update.model_id.is_some())
+            .bind(update.model_id.as_ref().and_then(Option::as_deref))
+            .bind(update.generation_params.as_ref().map(Json))
+            .bind(persona_id)
+            .execute(&mut *tx)
             .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(persona)
+    }
+
+    async fn delete_persona(&self, persona_id: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Sessions keep the settings they copied from the persona
+        sqlx::query("UPDATE session SET persona_id = NULL WHERE persona_id = ?")
+            .bind(persona_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM persona WHERE id = ?")
+            .bind(persona_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
 
         Ok(result.rows_affected() > 0)
     }
@@ -186,60 +1446,258 @@ impl MemoryRepo for SqliteProvider {
     // === Message Operations ===
 
     async fn save_message(&self, message: CreateMessage) -> Result<Message> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
+        let now_ms = unix_now_ms();
+        let mut tx = self.pool.begin().await?;
 
-        let result = sqlx::query(
-            "INSERT INTO message (session_id, role, content, ts, embedding, recall_score) VALUES (?, ?, ?, ?, ?, ?) RETURNING *"
-        )
+        ensure_session_writable(&mut *tx, message.session_id).await?;
+
+        // Append to the active branch unless an explicit parent starts a new one
+        let parent_id = match message.parent_id {
+            Some(parent_id) => {
+                let parent_session: Option<i64> = sqlx::query_scalar("SELECT session_id FROM message WHERE id = ? AND deleted_at IS NULL")
+                    .bind(parent_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                if parent_session != Some(message.session_id) {
+                    return Err(DatabaseError::Query(format!(
+                        "Parent message {} not found in session {}",
+                        parent_id, message.session_id
+                    )));
+                }
+                Some(parent_id)
+            }
+            None => sqlx::query_scalar("SELECT active_leaf_id FROM session WHERE id = ?")
+                .bind(message.session_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .flatten(),
+        };
+
+        let content = match &message.content_parts {
+            Some(parts) => ContentPart::plain_text(parts),
+            None => message.content.clone(),
+        };
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO message (session_id, role, content, ts, ts_ms, embedding, recall_score, parent_id, token_count, content_parts, reasoning_tokens, generation_params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING {}",
+            MESSAGE_COLUMNS
+        ))
         .bind(message.session_id)
-        .bind(&message.role)
-        .bind(&message.content)
-        .bind(now)
+        .bind(message.role)
+        .bind(&content)
+        .bind(now_ms / 1000)
+        .bind(now_ms)
         .bind(&message.embedding)
         .bind(message.recall_score)
-        .fetch_one(&self.pool)
+        .bind(parent_id)
+        .bind(message.token_count)
+        .bind(message.content_parts.as_ref().map(Json))
+        .bind(message.reasoning_tokens)
+        .bind(message.generation_params.as_ref().map(Json))
+        .fetch_one(&mut *tx)
         .await?;
 
-        Ok(Message {
-            id: result.get("id"),
-            session_id: result.get("session_id"),
-            role: result.get("role"),
-            content: result.get("content"),
-            ts: result.get("ts"),
-            embedding: result.get("embedding"),
-            recall_score: result.get("recall_score"),
-        })
+        let saved = message_from_row(&result);
+
+        sqlx::query("UPDATE session SET active_leaf_id = ? WHERE id = ?")
+            .bind(saved.id)
+            .bind(saved.session_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(saved)
     }
 
     async fn recent_messages(&self, session_id: i64, limit: Option<i64>) -> Result<Vec<Message>> {
-        let query = match limit {
-            Some(limit) => format!("SELECT * FROM message WHERE session_id = {} ORDER BY ts DESC LIMIT {}", session_id, limit),
-            None => format!("SELECT * FROM message WHERE session_id = {} ORDER BY ts DESC", session_id),
-        };
+        // Only the active branch is returned; a negative LIMIT means no limit in SQLite
+        let rows = sqlx::query(&format!(
+            "{} SELECT {} FROM message JOIN active_path ON id = node_id WHERE deleted_at IS NULL ORDER BY ts_ms DESC, id DESC LIMIT ?",
+            ACTIVE_PATH_CTE, MESSAGE_COLUMNS
+        ))
+        .bind(session_id)
+        .bind(limit.unwrap_or(-1))
+        .fetch_all(&self.pool)
+        .await?;
 
-        let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
-        
-        let messages = rows
-            .into_iter()
-            .map(|row| Message {
-                id: row.get("id"),
-                session_id: row.get("session_id"),
-                role: row.get("role"),
-                content: row.get("content"),
-                ts: row.get("ts"),
-                embedding: row.get("embedding"),
-                recall_score: row.get("recall_score"),
-            })
-            .collect();
+        let messages = rows.iter().map(message_from_row).collect();
 
         Ok(messages)
     }
 
+    async fn session_messages(&self, session_id: i64) -> Result<Vec<Message>> {
+        // Every branch, oldest first; parent_id links the tree
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM message WHERE session_id = ? AND deleted_at IS NULL ORDER BY ts_ms, id",
+            MESSAGE_COLUMNS
+        ))
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(message_from_row).collect())
+    }
+
+    async fn messages_page(&self, session_id: i64, cursor: MessageCursor) -> Result<MessagePage> {
+        let limit = cursor.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if limit <= 0 {
+            return Err(DatabaseError::Query("Page size must be positive".to_string()));
+        }
+
+        let anchor_id = match (cursor.before, cursor.after) {
+            (Some(_), Some(_)) => {
+                return Err(DatabaseError::Query(
+                    "Specify either a before or an after cursor, not both".to_string(),
+                ))
+            }
+            (Some(id), None) | (None, Some(id)) => Some(id),
+            (None, None) => None,
+        };
+
+        // A cursor message trashed since the page was loaded still marks its position
+        let anchor: Option<(i64, i64)> = match anchor_id {
+            Some(id) => {
+                let row: Option<(i64, i64)> = sqlx::query_as(
+                    "SELECT ts_ms, id FROM message WHERE id = ? AND session_id = ?"
+                )
+                .bind(id)
+                .bind(session_id)
+                .fetch_optional(&self.pool)
+                .await?;
+                Some(row.ok_or_else(|| DatabaseError::Query(format!(
+                    "Cursor message {} not found in session {}",
+                    id, session_id
+                )))?)
+            }
+            None => None,
+        };
+
+        // Page along the active branch, fetching one extra row to know whether another page exists
+        let mut rows = match (anchor, cursor.after.is_some()) {
+            (Some((ts_ms, id)), true) => {
+                sqlx::query(&format!(
+                    "{} SELECT {} FROM message JOIN active_path ON id = node_id WHERE deleted_at IS NULL AND (ts_ms, id) > (?, ?) ORDER BY ts_ms ASC, id ASC LIMIT ?",
+                    ACTIVE_PATH_CTE, MESSAGE_COLUMNS
+                ))
+                .bind(session_id)
+                .bind(ts_ms)
+                .bind(id)
+                .bind(limit + 1)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            (Some((ts_ms, id)), false) => {
+                sqlx::query(&format!(
+                    "{} SELECT {} FROM message JOIN active_path ON id = node_id WHERE deleted_at IS NULL AND (ts_ms, id) < (?, ?) ORDER BY ts_ms DESC, id DESC LIMIT ?",
+                    ACTIVE_PATH_CTE, MESSAGE_COLUMNS
+                ))
+                .bind(session_id)
+                .bind(ts_ms)
+                .bind(id)
+                .bind(limit + 1)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            (None, _) => {
+                sqlx::query(&format!(
+                    "{} SELECT {} FROM message JOIN active_path ON id = node_id WHERE deleted_at IS NULL ORDER BY ts_ms DESC, id DESC LIMIT ?",
+                    ACTIVE_PATH_CTE, MESSAGE_COLUMNS
+                ))
+                .bind(session_id)
+                .bind(limit + 1)
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        if cursor.after.is_some() {
+            rows.reverse();
+        }
+
+        Ok(MessagePage {
+            messages: rows.iter().map(message_from_row).collect(),
+            has_more,
+        })
+    }
+
+    async fn update_message(&self, message_id: i64, update: UpdateMessage) -> Result<Message> {
+        let mut tx = self.pool.begin().await?;
+
+        ensure_message_writable(&mut *tx, message_id).await?;
+
+        let (current, current_parts): (String, Option<String>) =
+            sqlx::query_as("SELECT content, content_parts FROM message WHERE id = ? AND deleted_at IS NULL")
+                .bind(message_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+        // Structured parts replace both columns; plain content drops any parts
+        let (content, content_parts) = match &update.content_parts {
+            Some(parts) => (Some(ContentPart::plain_text(parts)), serde_json::to_string(parts).ok()),
+            None => (update.content.clone(), None),
+        };
+        let content_changed = content.as_ref().is_some_and(|content| *content != current);
+        let parts_changed = match &update.content_parts {
+            Some(_) => content_parts != current_parts,
+            None => content_changed && current_parts.is_some(),
+        };
+
+        // Keep the previous content as a revision before overwriting it
+        if content_changed || parts_changed {
+            sqlx::query(
+                "INSERT INTO message_revision (message_id, content, content_parts, changed_by, revised_at) VALUES (?, ?, ?, ?, ?)"
+            )
+            .bind(message_id)
+            .bind(&current)
+            .bind(&current_parts)
+            .bind(&update.changed_by)
+            .bind(unix_now_ms() / 1000)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // An edited message keeps its embedding only if a new one is supplied
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE message SET
+                content = COALESCE(?1, content),
+                content_parts = CASE WHEN ?5 THEN ?6 ELSE content_parts END,
+                recall_score = CASE WHEN ?7 THEN ?2 ELSE recall_score END,
+                embedding = CASE
+                    WHEN ?8 THEN ?3
+                    WHEN ?1 IS NOT NULL AND ?1 <> content THEN NULL
+                    ELSE embedding
+                END
+            WHERE id = ?4
+            RETURNING {}
+            "#,
+            MESSAGE_COLUMNS
+        ))
+        .bind(&content)
+        .bind(update.recall_score.flatten())
+        .bind(update.embedding.as_ref().and_then(Option::as_ref))
+        .bind(message_id)
+        .bind(parts_changed)
+        .bind(&content_parts)
+        .bind(update.recall_score.is_some())
+        .bind(update.embedding.is_some())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(message_from_row(&row))
+    }
+
     async fn delete_message(&self, message_id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM message WHERE id = ?")
+        ensure_message_writable(&self.pool, message_id).await?;
+
+        // Trashed messages stay in the tree and are filtered from every read
+        let result = sqlx::query("UPDATE message SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(unix_now_ms() / 1000)
             .bind(message_id)
             .execute(&self.pool)
             .await?;
@@ -247,6 +1705,212 @@ impl MemoryRepo for SqliteProvider {
         Ok(result.rows_affected() > 0)
     }
 
+    async fn restore_message(&self, message_id: i64) -> Result<Message> {
+        ensure_message_writable(&self.pool, message_id).await?;
+
+        let row = sqlx::query(&format!(
+            "UPDATE message SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL RETURNING {}",
+            MESSAGE_COLUMNS
+        ))
+        .bind(message_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(message_from_row(&row))
+    }
+
+    async fn list_message_revisions(&self, message_id: i64) -> Result<Vec<MessageRevision>> {
+        let revisions = sqlx::query_as::<_, MessageRevision>(
+            "SELECT id, message_id, content, content_parts, changed_by, revised_at FROM message_revision WHERE message_id = ? ORDER BY revised_at DESC, id DESC"
+        )
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    async fn restore_message_revision(&self, revision_id: i64) -> Result<Message> {
+        let revision = sqlx::query_as::<_, MessageRevision>(
+            "SELECT id, message_id, content, content_parts, changed_by, revised_at FROM message_revision WHERE id = ?"
+        )
+        .bind(revision_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Restoring is itself an edit, so the content being replaced becomes a new revision
+        self.update_message(
+            revision.message_id,
+            UpdateMessage {
+                content: Some(revision.content),
+                content_parts: revision.content_parts,
+                changed_by: Some("restore".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    // === Branch Operations ===
+
+    async fn list_branches(&self, session_id: i64, parent_id: Option<i64>) -> Result<Vec<Message>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM message WHERE session_id = ? AND parent_id IS ? AND deleted_at IS NULL ORDER BY ts_ms ASC, id ASC",
+            MESSAGE_COLUMNS
+        ))
+        .bind(session_id)
+        .bind(parent_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(message_from_row).collect())
+    }
+
+    async fn switch_branch(&self, session_id: i64, message_id: i64) -> Result<Session> {
+        let owner: Option<i64> = sqlx::query_scalar("SELECT session_id FROM message WHERE id = ? AND deleted_at IS NULL")
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if owner != Some(session_id) {
+            return Err(DatabaseError::Query(format!(
+                "Message {} not found in session {}",
+                message_id, session_id
+            )));
+        }
+
+        // Follow the newest reply at each level down to a leaf
+        let mut leaf_id = message_id;
+        while let Some(child_id) = sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM message WHERE parent_id = ? AND deleted_at IS NULL ORDER BY ts_ms DESC, id DESC LIMIT 1"
+        )
+        .bind(leaf_id)
+        .fetch_optional(&self.pool)
+        .await?
+        {
+            leaf_id = child_id;
+        }
+
+        let row = sqlx::query(&format!("UPDATE session SET active_leaf_id = ? WHERE id = ? RETURNING {}", SESSION_COLUMNS))
+            .bind(leaf_id)
+            .bind(session_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(session_from_row(&row))
+    }
+
+    async fn transcript(&self, leaf_id: i64) -> Result<Vec<Message>> {
+        let rows = sqlx::query(&format!(
+            "{} SELECT {} FROM message JOIN path ON id = node_id WHERE deleted_at IS NULL ORDER BY depth DESC",
+            TRANSCRIPT_CTE, MESSAGE_COLUMNS
+        ))
+        .bind(leaf_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(message_from_row).collect())
+    }
+
+    // === Trash Operations ===
+
+    async fn list_trash(&self) -> Result<TrashContents> {
+        let sessions = sqlx::query(&format!(
+            "SELECT {} FROM session WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            SESSION_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let messages = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM message
+            WHERE deleted_at IS NOT NULL
+              AND session_id IN (SELECT id FROM session WHERE deleted_at IS NULL)
+            ORDER BY deleted_at DESC
+            "#,
+            MESSAGE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(TrashContents {
+            sessions: sessions.iter().map(session_from_row).collect(),
+            messages: messages.iter().map(message_from_row).collect(),
+        })
+    }
+
+    async fn purge_trash(&self, older_than_secs: i64) -> Result<PurgeReport> {
+        let cutoff = unix_now_ms() / 1000 - older_than_secs;
+        let mut report = PurgeReport::default();
+        let mut tx = self.pool.begin().await?;
+
+        // Trashed messages are spliced out of the tree so their replies stay reachable
+        let expired: Vec<(i64, i64, Option<i64>)> = sqlx::query_as(
+            "SELECT id, session_id, parent_id FROM message WHERE deleted_at IS NOT NULL AND deleted_at <= ? ORDER BY id DESC"
+        )
+        .bind(cutoff)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (message_id, session_id, parent_id) in expired {
+            sqlx::query("UPDATE message SET parent_id = ? WHERE parent_id = ?")
+                .bind(parent_id)
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("UPDATE session SET active_leaf_id = ? WHERE id = ? AND active_leaf_id = ?")
+                .bind(parent_id)
+                .bind(session_id)
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("DELETE FROM message_revision WHERE message_id = ?")
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+
+            report.messages_purged += sqlx::query("DELETE FROM message WHERE id = ?")
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected() as i64;
+        }
+
+        // Sessions take all of their messages with them
+        let expired_sessions = "SELECT id FROM session WHERE deleted_at IS NOT NULL AND deleted_at <= ?";
+
+        sqlx::query(&format!(
+            "DELETE FROM message_revision WHERE message_id IN (SELECT id FROM message WHERE session_id IN ({}))",
+            expired_sessions
+        ))
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?;
+
+        report.messages_purged += sqlx::query(&format!("DELETE FROM message WHERE session_id IN ({})", expired_sessions))
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+        sqlx::query(&format!("DELETE FROM session_tag WHERE session_id IN ({})", expired_sessions))
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+
+        report.sessions_purged = sqlx::query("DELETE FROM session WHERE deleted_at IS NOT NULL AND deleted_at <= ?")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+        tx.commit().await?;
+
+        Ok(report)
+    }
+
     // === Vector Search Operations ===
 
     async fn semantic_search(&self, _query_embedding: Vec<f32>, _limit: Option<i64>) -> Result<Vec<Message>> {
@@ -257,27 +1921,55 @@ impl MemoryRepo for SqliteProvider {
     // === Utility Operations ===
 
     async fn get_database_stats(&self) -> Result<DatabaseStats> {
-        let session_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM session")
+        let session_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM session WHERE deleted_at IS NULL")
             .fetch_one(&self.pool)
             .await?;
 
-        let message_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM message")
-            .fetch_one(&self.pool)
-            .await?;
+        let message_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM message WHERE deleted_at IS NULL AND session_id IN (SELECT id FROM session WHERE deleted_at IS NULL)"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let page_size = self.pragma_value("page_size").await?;
+        let page_count = self.pragma_value("page_count").await?;
+        let freelist_count = self.pragma_value("freelist_count").await?;
+        let (database_size, wal_size) = self.file_sizes().await?;
+
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut table_row_counts = BTreeMap::new();
+        for table in tables {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")))
+                .fetch_one(&self.pool)
+                .await?;
+            table_row_counts.insert(table, rows);
+        }
 
         Ok(DatabaseStats {
             session_count: session_count.0,
             message_count: message_count.0,
-            database_size_bytes: None,
+            database_size_bytes: Some(database_size),
             vector_index_size: None,
+            page_size,
+            page_count,
+            freelist_count,
+            reclaimable_bytes: freelist_count * page_size,
+            wal_size_bytes: wal_size,
+            table_row_counts,
         })
     }
 
     async fn clear_all_data(&self) -> Result<()> {
-        // Clear in order to respect foreign key constraints
-        sqlx::query("DELETE FROM message").execute(&self.pool).await?;
-        sqlx::query("DELETE FROM session").execute(&self.pool).await?;
-        
+        // Everything goes to the trash so a mistaken clear can be undone
+        sqlx::query("UPDATE session SET deleted_at = ? WHERE deleted_at IS NULL")
+            .bind(unix_now_ms() / 1000)
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
 }
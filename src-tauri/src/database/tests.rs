@@ -0,0 +1,1936 @@
+//! Database tests against in-memory SQLite
+
+use crate::connectors::openrouter::chat_request;
+use crate::connectors::ModelLimits;
+use crate::database::migrations::{InitialMigration, Migration};
+use crate::database::backup::{rekey_backups, restore_backup, rotate_backups, BackupSchedule};
+use crate::database::commands::{database, DatabaseState};
+use crate::database::export::{export_session, ExportFormat};
+use crate::database::importers::{import_conversations, ImportSource};
+use crate::database::models::{
+    CheckpointMode, ContentPart, CreateMessage, CreatePersona, CreateSession, GenerationParams, LegacyMemoryFields, Message,
+    MessageCursor, MessageRole, SessionQuery, SessionSort, SessionStatus, UpdateMessage, UpdatePersona, UpdateSession,
+};
+use crate::database::persona_files::{export_personas, read_personas};
+use crate::database::providers::sqlite::SqliteProvider;
+use crate::database::{
+    encryption, DatabaseConfig, DatabaseError, DatabaseManager, DatabaseProvider, MemoryRepo, PoolOptions,
+};
+use crate::paths::DataDirSource;
+use crate::workspaces::{active_workspace, create_workspace, delete_workspace, get_active_workspace, list_workspaces};
+use sqlx::Row;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Provider for a fresh in-memory database, migrated up to `version`
+async fn provider_at(version: i32) -> SqliteProvider {
+    let provider = SqliteProvider::new(":memory:").await.unwrap();
+    provider.migrate_to(version).await.unwrap();
+    provider
+}
+
+/// Manager for a fresh, unmigrated in-memory database
+async fn memory_manager() -> DatabaseManager {
+    DatabaseManager::new(DatabaseConfig {
+        provider: DatabaseProvider::SQLite,
+        connection_string: ":memory:".to_string(),
+        passphrase: None,
+        pool: Default::default(),
+    })
+    .await
+    .unwrap()
+}
+
+/// Provider for a fresh, fully migrated in-memory database
+async fn provider() -> SqliteProvider {
+    let provider = SqliteProvider::new(":memory:").await.unwrap();
+    provider.migrate().await.unwrap();
+    provider
+}
+
+async fn new_session(provider: &SqliteProvider) -> i64 {
+    provider
+        .create_session(CreateSession {
+            name: "Chat".to_string(),
+            role: None,
+            goals: None,
+            llm_provider: None,
+            model_id: None,
+            status: None,
+            persona_id: None,
+        })
+        .await
+        .unwrap()
+        .id
+}
+
+/// Add a message below `parent_id`, or below the active leaf when `None`
+async fn add_message(provider: &SqliteProvider, session_id: i64, content: &str, parent_id: Option<i64>) -> Message {
+    provider
+        .save_message(CreateMessage {
+            session_id,
+            role: MessageRole::User,
+            content: content.to_string(),
+            embedding: None,
+            recall_score: None,
+            parent_id,
+            token_count: None,
+            content_parts: None,
+            reasoning_tokens: None,
+            generation_params: None,
+        })
+        .await
+        .unwrap()
+}
+
+/// A linear conversation of `n` messages, oldest first
+async fn linear_session(provider: &SqliteProvider, n: usize) -> (i64, Vec<i64>) {
+    let session_id = new_session(provider).await;
+    let mut ids = Vec::new();
+    for i in 0..n {
+        ids.push(add_message(provider, session_id, &format!("Message {}", i + 1), None).await.id);
+    }
+    (session_id, ids)
+}
+
+fn ids(messages: &[Message]) -> Vec<i64> {
+    messages.iter().map(|message| message.id).collect()
+}
+
+fn cursor(before: Option<i64>, after: Option<i64>, limit: i64) -> MessageCursor {
+    MessageCursor {
+        before,
+        after,
+        limit: Some(limit),
+    }
+}
+
+async fn count(provider: &SqliteProvider, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(provider.pool()).await.unwrap()
+}
+
+async fn parent_of(provider: &SqliteProvider, message_id: i64) -> Option<i64> {
+    sqlx::query_scalar("SELECT parent_id FROM message WHERE id = ?")
+        .bind(message_id)
+        .fetch_one(provider.pool())
+        .await
+        .unwrap()
+}
+
+async fn active_leaf(provider: &SqliteProvider, session_id: i64) -> Option<i64> {
+    provider.get_session_by_id(session_id).await.unwrap().active_leaf_id
+}
+
+// === Configuration ===
+
+#[test]
+fn memory_config_paths_are_resolved() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var(crate::paths::HOME_ENV, home.path());
+
+    let path_for = |path: &str| {
+        DatabaseConfig::from_memory_config(&serde_json::json!({ "provider": "sqlite", "config": { "path": path } }))
+            .unwrap()
+            .connection_string
+    };
+    let user_home = crate::paths::home_dir();
+
+    assert_eq!(path_for("~/chats/conv.db"), user_home.join("chats/conv.db").to_string_lossy());
+    assert_eq!(path_for("db/other.db"), home.path().join("db/other.db").to_string_lossy());
+    assert_eq!(path_for("/srv/conv.db"), "/srv/conv.db");
+    assert_eq!(path_for(":memory:"), ":memory:");
+
+    let unsupported = DatabaseConfig::from_memory_config(&serde_json::json!({ "provider": "supabase", "config": {} }));
+    assert!(unsupported.is_err());
+
+    std::env::remove_var(crate::paths::HOME_ENV);
+}
+
+// === Data directory ===
+
+const DIR_ENV_VARS: [&str; 4] = ["HOME", "XDG_DATA_HOME", "XDG_CONFIG_HOME", crate::paths::HOME_ENV];
+
+/// Points home and the XDG directories into `root`, restoring them when dropped
+struct IsolatedDirs(Vec<(&'static str, Option<std::ffi::OsString>)>);
+
+impl IsolatedDirs {
+    fn new(root: &Path) -> Self {
+        let saved = DIR_ENV_VARS.iter().map(|&name| (name, std::env::var_os(name))).collect();
+        std::env::set_var("HOME", root.join("home"));
+        std::env::set_var("XDG_DATA_HOME", root.join("data"));
+        std::env::set_var("XDG_CONFIG_HOME", root.join("config"));
+        std::env::remove_var(crate::paths::HOME_ENV);
+        Self(saved)
+    }
+}
+
+impl Drop for IsolatedDirs {
+    fn drop(&mut self) {
+        for (name, value) in &self.0 {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+    }
+}
+
+fn write_json(path: &Path, value: &serde_json::Value) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, serde_json::to_string_pretty(value).unwrap()).unwrap();
+}
+
+fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn data_dir_resolution_order() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    let resolved = crate::paths::resolve_data_dir();
+    assert_eq!(resolved.source, DataDirSource::Default);
+    assert_eq!(resolved.path, root.path().join("data/openconverse"));
+
+    // An existing legacy directory wins over XDG
+    std::fs::create_dir_all(root.path().join("home/.openconv")).unwrap();
+    assert_eq!(crate::paths::data_dir(), root.path().join("home/.openconv"));
+
+    let moved = root.path().join("moved");
+    write_json(&root.path().join("config/openconverse/location.json"), &serde_json::json!({ "data_dir": moved }));
+    let resolved = crate::paths::resolve_data_dir();
+    assert_eq!(resolved.source, DataDirSource::Configured);
+    assert_eq!(resolved.path, moved);
+
+    std::env::set_var(crate::paths::HOME_ENV, root.path().join("env"));
+    let resolved = crate::paths::resolve_data_dir();
+    assert_eq!(resolved.source, DataDirSource::Environment);
+    assert_eq!(resolved.path, root.path().join("env"));
+}
+
+#[test]
+fn moving_the_data_dir_rewrites_paths_inside_it() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    let current = crate::paths::data_dir();
+    std::fs::create_dir_all(current.join("db")).unwrap();
+    std::fs::write(current.join("db/conv.db"), b"data").unwrap();
+    write_json(
+        &current.join("settings/settings.json"),
+        &serde_json::json!({
+            "providers": [],
+            "memory_config": { "provider": "sqlite", "config": {
+                "path": current.join("db/conv.db"),
+                "backup": { "directory": "/srv/backups" },
+            } },
+        }),
+    );
+    write_json(
+        &current.join("workspaces/work/settings/settings.json"),
+        &serde_json::json!({
+            "providers": [],
+            "memory_config": { "provider": "sqlite", "config": { "path": current.join("workspaces/work/db/conv.db") } },
+        }),
+    );
+
+    let target = root.path().join("elsewhere/openconverse");
+    assert!(crate::paths::move_data_dir(Path::new("relative")).is_err());
+    assert!(crate::paths::move_data_dir(&current.join("nested")).is_err());
+    std::fs::create_dir_all(&target).unwrap();
+    std::fs::write(target.join("occupied"), b"").unwrap();
+    assert!(crate::paths::move_data_dir(&target).is_err());
+    std::fs::remove_file(target.join("occupied")).unwrap();
+
+    assert_eq!(crate::paths::move_data_dir(&target).unwrap(), target);
+    assert!(!current.exists());
+    assert_eq!(std::fs::read(target.join("db/conv.db")).unwrap(), b"data");
+    let resolved = crate::paths::resolve_data_dir();
+    assert_eq!(resolved.source, DataDirSource::Configured);
+    assert_eq!(resolved.path, target);
+
+    let settings = read_json(&target.join("settings/settings.json"));
+    assert_eq!(settings.pointer("/memory_config/config/path").unwrap(), &serde_json::json!(target.join("db/conv.db")));
+    assert_eq!(settings.pointer("/memory_config/config/backup/directory").unwrap(), "/srv/backups");
+    let settings = read_json(&target.join("workspaces/work/settings/settings.json"));
+    assert_eq!(
+        settings.pointer("/memory_config/config/path").unwrap(),
+        &serde_json::json!(target.join("workspaces/work/db/conv.db"))
+    );
+
+    // Moving back to the default location forgets the recorded one
+    assert_eq!(crate::paths::move_data_dir(&current).unwrap(), current);
+    assert!(!root.path().join("config/openconverse/location.json").exists());
+    assert_eq!(crate::paths::resolve_data_dir().source, DataDirSource::Default);
+
+    std::env::set_var(crate::paths::HOME_ENV, root.path().join("env"));
+    assert!(crate::paths::move_data_dir(&target).is_err());
+}
+
+// === Workspaces ===
+
+#[test]
+fn workspace_ids_never_collide() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    let first = create_workspace("Client Work".to_string()).unwrap();
+    let second = create_workspace("client work!".to_string()).unwrap();
+    assert_eq!(first.id, "client-work");
+    assert_eq!(second.id, "client-work-2");
+    assert_eq!(create_workspace("Default".to_string()).unwrap().id, "default-2");
+    assert_eq!(create_workspace("???".to_string()).unwrap().id, "workspace");
+    assert!(create_workspace("  ".to_string()).is_err());
+
+    // A directory left behind without a registry entry is not reused
+    std::fs::create_dir_all(crate::paths::workspaces_dir().join("notes")).unwrap();
+    assert_eq!(create_workspace("Notes".to_string()).unwrap().id, "notes-2");
+
+    let ids: Vec<_> = list_workspaces().unwrap().into_iter().map(|workspace| workspace.id).collect();
+    assert_eq!(ids, ["default", "client-work", "client-work-2", "default-2", "workspace", "notes-2"]);
+    assert!(second.attachments_dir.is_dir());
+}
+
+#[test]
+fn default_and_active_workspaces_cannot_be_deleted() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    let work = create_workspace("Work".to_string()).unwrap();
+    let scratch = create_workspace("Scratch".to_string()).unwrap();
+    let registry = crate::paths::data_dir().join("workspaces.json");
+    let mut saved = read_json(&registry);
+    saved["active"] = serde_json::json!(work.id);
+    write_json(&registry, &saved);
+
+    assert!(delete_workspace("default".to_string()).is_err());
+    assert!(delete_workspace(work.id.clone()).is_err());
+    assert_eq!(get_active_workspace().unwrap().id, work.id);
+
+    let scratch_dir = crate::paths::workspaces_dir().join(&scratch.id);
+    std::fs::write(scratch_dir.join("attachments").join("photo.png"), b"png").unwrap();
+    assert!(delete_workspace(scratch.id.clone()).unwrap());
+    assert!(!scratch_dir.exists(), "the workspace's files are removed");
+    assert!(!delete_workspace(scratch.id).unwrap(), "already gone");
+
+    let ids: Vec<_> = list_workspaces().unwrap().into_iter().map(|workspace| workspace.id).collect();
+    assert_eq!(ids, ["default", "work"]);
+    assert!(crate::paths::workspaces_dir().join(&work.id).is_dir());
+}
+
+#[test]
+fn registry_without_the_default_workspace_is_repaired_on_load() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    write_json(
+        &crate::paths::data_dir().join("workspaces.json"),
+        &serde_json::json!({
+            "active": "missing",
+            "workspaces": [{ "id": "work", "name": "Work", "created_at": 1 }],
+        }),
+    );
+
+    let workspaces = list_workspaces().unwrap();
+    let ids: Vec<_> = workspaces.iter().map(|workspace| workspace.id.as_str()).collect();
+    assert_eq!(ids, ["default", "work"]);
+    let active = get_active_workspace().unwrap();
+    assert_eq!(active.id, "default");
+    assert_eq!(active.db_path, crate::paths::data_dir().join("db").join("conv.db"));
+    assert_eq!(active_workspace().personas_dir(), crate::paths::data_dir().join("personas"));
+}
+
+// === Migrations ===
+
+/// id, session_id, role, content, ts_ms, parent_id, deleted_at, token_count
+type MessageRow = (i64, i64, String, String, i64, Option<i64>, Option<i64>, Option<i64>);
+
+#[tokio::test]
+async fn message_role_rebuild_keeps_rows_revisions_and_foreign_keys() {
+    let provider = provider_at(10).await;
+    sqlx::raw_sql(
+        r#"
+        INSERT INTO session (id, name) VALUES (1, 'Chat'), (2, 'Fork');
+        INSERT INTO message (id, session_id, role, content, ts_ms, parent_id, token_count) VALUES
+            (1, 1, 'user', 'Hello', 1000, NULL, 2),
+            (2, 1, 'assistant', 'Hi there', 2000, 1, 3),
+            (3, 1, 'assistant', 'Hey', 3000, 1, NULL);
+        UPDATE message SET deleted_at = 5 WHERE id = 3;
+        UPDATE session SET active_leaf_id = 2 WHERE id = 1;
+        UPDATE session SET forked_from_session_id = 1, forked_from_message_id = 2 WHERE id = 2;
+        INSERT INTO message_revision (message_id, content, changed_by, revised_at) VALUES (2, 'Hi', 'user', 1);
+        "#,
+    )
+    .execute(provider.pool())
+    .await
+    .unwrap();
+
+    provider.migrate().await.unwrap();
+
+    let rows = sqlx::query("SELECT id, session_id, role, content, ts_ms, parent_id, deleted_at, token_count FROM message ORDER BY id")
+        .fetch_all(provider.pool())
+        .await
+        .unwrap();
+    let rows: Vec<MessageRow> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get("id"),
+                row.get("session_id"),
+                row.get("role"),
+                row.get("content"),
+                row.get("ts_ms"),
+                row.get("parent_id"),
+                row.get("deleted_at"),
+                row.get("token_count"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (1, 1, "user".to_string(), "Hello".to_string(), 1000, None, None, Some(2)),
+            (2, 1, "assistant".to_string(), "Hi there".to_string(), 2000, Some(1), None, Some(3)),
+            (3, 1, "assistant".to_string(), "Hey".to_string(), 3000, Some(1), Some(5), None),
+        ]
+    );
+
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message_revision WHERE message_id = 2").await, 1);
+    assert_eq!(count(&provider, "SELECT active_leaf_id FROM session WHERE id = 1").await, 2);
+    assert_eq!(count(&provider, "SELECT forked_from_message_id FROM session WHERE id = 2").await, 2);
+
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(provider.pool())
+        .await
+        .unwrap();
+    assert!(violations.is_empty());
+
+    // The rebuilt table keeps its foreign keys and accepts the new roles
+    sqlx::query("INSERT INTO message (session_id, role, content, parent_id) VALUES (1, 'tool', '{}', 2)")
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    assert!(sqlx::query("INSERT INTO message (session_id, role, content) VALUES (99, 'user', 'orphan')")
+        .execute(provider.pool())
+        .await
+        .is_err());
+
+    // Revisions still cascade from the rebuilt table
+    sqlx::query("DELETE FROM message WHERE id = 2")
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message_revision").await, 0);
+}
+
+#[tokio::test]
+async fn every_message_role_round_trips() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+
+    for role in [MessageRole::User, MessageRole::Assistant, MessageRole::System, MessageRole::Tool, MessageRole::Developer] {
+        let json = serde_json::to_value(role).unwrap();
+        assert_eq!(json, serde_json::json!(role.as_str()));
+        assert_eq!(serde_json::from_value::<MessageRole>(json).unwrap(), role);
+
+        let saved = provider
+            .save_message(CreateMessage {
+                session_id,
+                role,
+                content: role.to_string(),
+                embedding: None,
+                recall_score: None,
+                parent_id: None,
+                token_count: None,
+                content_parts: None,
+                reasoning_tokens: None,
+                generation_params: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(saved.role, role);
+    }
+
+    let stored: Vec<String> = sqlx::query_scalar("SELECT role FROM message ORDER BY id")
+        .fetch_all(provider.pool())
+        .await
+        .unwrap();
+    assert_eq!(stored, vec!["user", "assistant", "system", "tool", "developer"]);
+
+    // Unknown roles are rejected by serde and by the table
+    assert!(serde_json::from_value::<MessageRole>(serde_json::json!("robot")).is_err());
+    assert!(serde_json::from_value::<MessageRole>(serde_json::json!("User")).is_err());
+    assert!(sqlx::query("INSERT INTO message (session_id, role, content) VALUES (?, 'robot', 'beep')")
+        .bind(session_id)
+        .execute(provider.pool())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn legacy_tables_are_imported_once() {
+    let manager = memory_manager().await;
+    let provider = &manager.provider;
+    sqlx::raw_sql(InitialMigration.up_sql())
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    sqlx::raw_sql(
+        r#"
+        INSERT INTO long_term_memory (content, created_at, metadata) VALUES
+            ('Prefers metric units', '2024-01-01 10:00:00', NULL),
+            ('Lives in Lisbon', '2024-01-02 10:00:00', '{"source":"profile"}');
+        INSERT INTO short_term_memory (content, expires_at) VALUES ('Asked about trains', '2024-02-01 00:00:00');
+        INSERT INTO vector_db (document_id, content, collection_name) VALUES ('doc-1', 'Manual', 'docs');
+        "#,
+    )
+    .execute(provider.pool())
+    .await
+    .unwrap();
+
+    let report = manager.migrate().await.unwrap().expect("legacy tables were present");
+    assert_eq!(report.long_term_messages, 2);
+    assert_eq!(report.short_term_messages, 1);
+    assert_eq!(report.knowledge_documents, 1);
+    assert_eq!(report.sessions_created, 2);
+
+    // Fields without a column are reported against the message they belonged to
+    let lisbon = count(provider, "SELECT id FROM message WHERE content = 'Lives in Lisbon'").await;
+    let trains = count(provider, "SELECT id FROM message WHERE content = 'Asked about trains'").await;
+    assert_eq!(
+        report.unmapped_fields,
+        vec![
+            LegacyMemoryFields {
+                message_id: lisbon,
+                expires_at: None,
+                metadata: Some(r#"{"source":"profile"}"#.to_string()),
+            },
+            LegacyMemoryFields {
+                message_id: trains,
+                expires_at: Some("2024-02-01 00:00:00".to_string()),
+                metadata: None,
+            },
+        ]
+    );
+
+    let legacy_tables = count(
+        provider,
+        "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('long_term_memory', 'short_term_memory', 'vector_db')",
+    )
+    .await;
+    assert_eq!(legacy_tables, 0);
+    assert_eq!(count(provider, "SELECT COUNT(*) FROM message WHERE role = 'system'").await, 3);
+    assert_eq!(count(provider, "SELECT COUNT(*) FROM knowledge WHERE document_id = 'doc-1'").await, 1);
+
+    // Imported memories form one linear branch ending at the active leaf
+    let leaf = count(provider, "SELECT active_leaf_id FROM session WHERE name = 'Imported long-term memory'").await;
+    let root = count(provider, "SELECT parent_id FROM message WHERE id = (SELECT active_leaf_id FROM session WHERE name = 'Imported long-term memory')").await;
+    assert_eq!(count(provider, &format!("SELECT COUNT(*) FROM message WHERE id = {} AND parent_id IS NULL", root)).await, 1);
+    assert_ne!(leaf, root);
+
+    // A second open finds nothing left to import
+    assert!(manager.migrate().await.unwrap().is_none());
+    assert_eq!(count(provider, "SELECT COUNT(*) FROM session").await, 2);
+    assert_eq!(count(provider, "SELECT COUNT(*) FROM message").await, 3);
+}
+
+#[tokio::test]
+async fn edits_record_revisions_in_seconds() {
+    let provider = provider().await;
+    let (_, message_ids) = linear_session(&provider, 1).await;
+    let before = chrono::Utc::now().timestamp();
+
+    provider
+        .update_message(
+            message_ids[0],
+            UpdateMessage {
+                content: Some("Edited".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let revisions = provider.list_message_revisions(message_ids[0]).await.unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].content, "Message 1");
+    assert!((before..=before + 5).contains(&revisions[0].revised_at));
+}
+
+#[tokio::test]
+async fn orphan_cleanup_removes_dangling_rows_and_clears_dangling_links() {
+    let provider = provider_at(16).await;
+    // Written with foreign keys off, as older builds did
+    sqlx::raw_sql(
+        r#"
+        PRAGMA foreign_keys = OFF;
+        INSERT INTO session (id, name) VALUES (1, 'Kept');
+        INSERT INTO message (id, session_id, role, content, ts_ms, parent_id) VALUES
+            (1, 1, 'user', 'Kept', 1000, NULL),
+            (2, 1, 'assistant', 'Reply to a lost message', 2000, 99),
+            (3, 2, 'user', 'In a lost session', 3000, NULL);
+        INSERT INTO message_revision (message_id, content, revised_at) VALUES (1, 'Kep', 1), (99, 'Lost', 1);
+        INSERT INTO tag (id, name) VALUES (1, 'kept');
+        INSERT INTO session_tag (session_id, tag_id) VALUES (1, 1), (1, 2), (2, 1);
+        UPDATE session SET
+            active_leaf_id = 99,
+            forked_from_session_id = 2,
+            forked_from_message_id = 99,
+            folder_id = 5,
+            persona_id = 7
+        WHERE id = 1;
+        PRAGMA foreign_keys = ON;
+        "#,
+    )
+    .execute(provider.pool())
+    .await
+    .unwrap();
+    assert!(provider.integrity_check().await.unwrap().foreign_key_violations > 0);
+
+    provider.migrate().await.unwrap();
+
+    let integrity = provider.integrity_check().await.unwrap();
+    assert!(integrity.ok, "{:?}", integrity);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM session").await, 1);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 2);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message_revision").await, 1);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM session_tag").await, 1);
+    assert_eq!(parent_of(&provider, 2).await, None);
+
+    let linked = count(
+        &provider,
+        "SELECT COUNT(*) FROM session
+         WHERE COALESCE(active_leaf_id, forked_from_session_id, forked_from_message_id, folder_id, persona_id) IS NOT NULL",
+    )
+    .await;
+    assert_eq!(linked, 0);
+}
+
+// === Message pagination ===
+
+#[tokio::test]
+async fn message_page_of_empty_session_is_empty() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+
+    let page = provider.messages_page(session_id, MessageCursor::default()).await.unwrap();
+    assert!(page.messages.is_empty());
+    assert!(!page.has_more);
+}
+
+#[tokio::test]
+async fn message_page_past_the_oldest_message_is_empty() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 3).await;
+
+    let page = provider.messages_page(session_id, cursor(Some(message_ids[0]), None, 10)).await.unwrap();
+    assert!(page.messages.is_empty());
+    assert!(!page.has_more);
+
+    let page = provider.messages_page(session_id, cursor(None, Some(message_ids[2]), 10)).await.unwrap();
+    assert!(page.messages.is_empty());
+    assert!(!page.has_more);
+}
+
+#[tokio::test]
+async fn message_pages_on_an_exact_boundary_report_no_more() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 4).await;
+
+    let page = provider.messages_page(session_id, cursor(None, None, 4)).await.unwrap();
+    assert_eq!(ids(&page.messages), vec![message_ids[3], message_ids[2], message_ids[1], message_ids[0]]);
+    assert!(!page.has_more);
+
+    let first = provider.messages_page(session_id, cursor(None, None, 2)).await.unwrap();
+    assert_eq!(ids(&first.messages), vec![message_ids[3], message_ids[2]]);
+    assert!(first.has_more);
+
+    let second = provider.messages_page(session_id, cursor(Some(message_ids[2]), None, 2)).await.unwrap();
+    assert_eq!(ids(&second.messages), vec![message_ids[1], message_ids[0]]);
+    assert!(!second.has_more);
+
+    // Paging forward returns newer messages, still newest first
+    let newer = provider.messages_page(session_id, cursor(None, Some(message_ids[1]), 2)).await.unwrap();
+    assert_eq!(ids(&newer.messages), vec![message_ids[3], message_ids[2]]);
+    assert!(!newer.has_more);
+}
+
+#[tokio::test]
+async fn message_page_from_a_trashed_cursor_keeps_its_position() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 4).await;
+    assert!(provider.delete_message(message_ids[2]).await.unwrap());
+
+    let older = provider.messages_page(session_id, cursor(Some(message_ids[2]), None, 10)).await.unwrap();
+    assert_eq!(ids(&older.messages), vec![message_ids[1], message_ids[0]]);
+    assert!(!older.has_more);
+
+    let newer = provider.messages_page(session_id, cursor(None, Some(message_ids[1]), 10)).await.unwrap();
+    assert_eq!(ids(&newer.messages), vec![message_ids[3]]);
+
+    // A cursor from another session is still rejected
+    let other_session = new_session(&provider).await;
+    assert!(provider.messages_page(other_session, cursor(Some(message_ids[2]), None, 10)).await.is_err());
+}
+
+#[tokio::test]
+async fn message_page_rejects_bad_cursors() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 2).await;
+
+    assert!(provider.messages_page(session_id, cursor(None, None, 0)).await.is_err());
+    assert!(provider.messages_page(session_id, cursor(Some(message_ids[0]), Some(message_ids[1]), 10)).await.is_err());
+    assert!(provider.messages_page(session_id, cursor(Some(9999), None, 10)).await.is_err());
+}
+
+// === Updates ===
+
+#[tokio::test]
+async fn session_updates_patch_only_the_given_fields() {
+    let provider = provider().await;
+    let session = provider
+        .create_session(CreateSession {
+            name: "Chat".to_string(),
+            role: Some("Tutor".to_string()),
+            goals: Some("Teach Rust".to_string()),
+            llm_provider: Some("openrouter".to_string()),
+            model_id: Some("model-a".to_string()),
+            status: None,
+            persona_id: None,
+        })
+        .await
+        .unwrap();
+
+    let renamed = provider
+        .update_session(session.id, UpdateSession {
+            name: Some("Renamed".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(renamed.name, "Renamed");
+    assert_eq!(renamed.role.as_deref(), Some("Tutor"));
+    assert_eq!(renamed.model_id.as_deref(), Some("model-a"));
+
+    // `null` clears a field, an absent one is kept
+    let update: UpdateSession = serde_json::from_value(serde_json::json!({ "role": null, "model_id": "model-b" })).unwrap();
+    assert_eq!(update.role, Some(None));
+    assert_eq!(update.goals, None);
+    let cleared = provider.update_session(session.id, update).await.unwrap();
+    assert_eq!(cleared.name, "Renamed");
+    assert_eq!(cleared.role, None);
+    assert_eq!(cleared.goals.as_deref(), Some("Teach Rust"));
+    assert_eq!(cleared.llm_provider.as_deref(), Some("openrouter"));
+    assert_eq!(cleared.model_id.as_deref(), Some("model-b"));
+}
+
+#[tokio::test]
+async fn persona_updates_bump_updated_at_and_clear_fields() {
+    let provider = provider().await;
+    let persona = provider
+        .create_persona(CreatePersona {
+            name: "Coach".to_string(),
+            system_prompt: Some("Be encouraging".to_string()),
+            goals: None,
+            llm_provider: None,
+            model_id: None,
+            generation_params: Default::default(),
+            avatar: Some("coach.png".to_string()),
+        })
+        .await
+        .unwrap();
+    sqlx::query("UPDATE persona SET updated_at = 1 WHERE id = ?")
+        .bind(persona.id)
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    let before = chrono::Utc::now().timestamp();
+
+    let updated = provider
+        .update_persona(
+            persona.id,
+            UpdatePersona {
+                avatar: Some(None),
+                ..Default::default()
+            },
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.avatar, None);
+    assert_eq!(updated.system_prompt.as_deref(), Some("Be encouraging"));
+    assert_eq!(updated.created_at, persona.created_at);
+    assert!((before..=before + 5).contains(&updated.updated_at));
+}
+
+#[tokio::test]
+async fn persona_export_keeps_names_that_slugify_alike_apart() {
+    let source = provider().await;
+    for (name, prompt) in [("Coach", "Be encouraging"), ("coach!", "Be blunt")] {
+        source
+            .create_persona(CreatePersona {
+                name: name.to_string(),
+                system_prompt: Some(prompt.to_string()),
+                goals: None,
+                llm_provider: None,
+                model_id: None,
+                generation_params: Default::default(),
+                avatar: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let written = export_personas(source.list_personas().await.unwrap(), dir.path()).unwrap();
+    assert_eq!(written.len(), 2);
+    assert_ne!(written[0], written[1]);
+
+    let imported = provider().await;
+    for persona in read_personas(dir.path()).unwrap() {
+        imported.upsert_persona(persona).await.unwrap();
+    }
+    let mut personas: Vec<_> = imported
+        .list_personas()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|persona| (persona.name, persona.system_prompt.unwrap()))
+        .collect();
+    personas.sort();
+    assert_eq!(
+        personas,
+        [
+            ("Coach".to_string(), "Be encouraging".to_string()),
+            ("coach!".to_string(), "Be blunt".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn message_edits_clear_the_embedding_unless_one_is_given() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+    let message = provider
+        .save_message(CreateMessage {
+            session_id,
+            role: MessageRole::User,
+            content: "Original".to_string(),
+            embedding: Some(vec![1, 2, 3]),
+            recall_score: Some(0.5),
+            parent_id: None,
+            token_count: None,
+            content_parts: None,
+            reasoning_tokens: None,
+            generation_params: None,
+        })
+        .await
+        .unwrap();
+
+    // Only the recall score changes; the content and its embedding stay
+    let scored = provider
+        .update_message(message.id, UpdateMessage {
+            recall_score: Some(Some(0.9)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(scored.content, "Original");
+    assert_eq!(scored.recall_score, Some(0.9));
+    assert_eq!(scored.embedding, Some(vec![1, 2, 3]));
+
+    let edited = provider
+        .update_message(message.id, UpdateMessage {
+            content: Some("Edited".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(edited.content, "Edited");
+    assert_eq!(edited.embedding, None);
+    assert_eq!(edited.recall_score, Some(0.9));
+
+    let reembedded = provider
+        .update_message(message.id, UpdateMessage {
+            content: Some("Edited again".to_string()),
+            embedding: Some(Some(vec![4, 5])),
+            recall_score: Some(None),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(reembedded.embedding, Some(vec![4, 5]));
+    assert_eq!(reembedded.recall_score, None);
+}
+
+// === Session organisation ===
+
+async fn named_session(provider: &SqliteProvider, name: &str) -> i64 {
+    let session_id = new_session(provider).await;
+    provider
+        .update_session(session_id, UpdateSession {
+            name: Some(name.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    session_id
+}
+
+async fn listed(provider: &SqliteProvider, query: SessionQuery) -> Vec<i64> {
+    provider
+        .get_sessions(query)
+        .await
+        .unwrap()
+        .iter()
+        .map(|session| session.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn session_listings_filter_by_folder_tag_pin_and_archive() {
+    let provider = provider().await;
+    let alpha = named_session(&provider, "alpha").await;
+    let beta = named_session(&provider, "Beta").await;
+    let gamma = named_session(&provider, "gamma").await;
+
+    let work = provider.create_folder("Work".to_string()).await.unwrap();
+    assert_eq!(provider.set_session_folder(alpha, Some(work.id)).await.unwrap().folder_id, Some(work.id));
+    let tagged = provider
+        .set_session_tags(alpha, vec!["rust".to_string(), " work ".to_string(), String::new()])
+        .await
+        .unwrap();
+    let mut tags = tagged.tags.clone();
+    tags.sort();
+    assert_eq!(tags, vec!["rust", "work"]);
+    provider.set_session_tags(beta, vec!["rust".to_string()]).await.unwrap();
+    assert!(provider.set_session_pinned(gamma, true).await.unwrap().pinned);
+    provider.set_session_status(beta, SessionStatus::Archived).await.unwrap();
+
+    // Archived sessions are hidden by default and pinned ones come first
+    assert_eq!(listed(&provider, SessionQuery::default()).await, vec![gamma, alpha]);
+    let with_archived = SessionQuery {
+        include_archived: true,
+        ..Default::default()
+    };
+    assert_eq!(listed(&provider, with_archived.clone()).await, vec![gamma, beta, alpha]);
+
+    let in_folder = SessionQuery {
+        folder_id: Some(work.id),
+        ..Default::default()
+    };
+    assert_eq!(listed(&provider, in_folder).await, vec![alpha]);
+    let tagged_rust = SessionQuery {
+        tag: Some("rust".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(listed(&provider, tagged_rust.clone()).await, vec![alpha]);
+    let tagged_rust = SessionQuery {
+        include_archived: true,
+        ..tagged_rust
+    };
+    assert_eq!(listed(&provider, tagged_rust).await, vec![beta, alpha]);
+    let pinned = SessionQuery {
+        pinned: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(listed(&provider, pinned).await, vec![gamma]);
+    let unpinned = SessionQuery {
+        pinned: Some(false),
+        include_archived: true,
+        ..Default::default()
+    };
+    assert_eq!(listed(&provider, unpinned).await, vec![beta, alpha]);
+
+    let by_name = SessionQuery {
+        sort: SessionSort::Name,
+        pinned_first: Some(false),
+        ..with_archived
+    };
+    assert_eq!(listed(&provider, by_name).await, vec![alpha, beta, gamma]);
+
+    // Unused tags are dropped, and deleting a folder keeps its sessions
+    provider.set_session_tags(alpha, vec!["rust".to_string()]).await.unwrap();
+    assert_eq!(provider.list_tags().await.unwrap(), vec!["rust"]);
+    assert!(provider.delete_folder(work.id).await.unwrap());
+    assert_eq!(provider.get_session_by_id(alpha).await.unwrap().folder_id, None);
+}
+
+#[tokio::test]
+async fn session_summaries_report_activity_of_live_messages() {
+    let provider = provider().await;
+    let quiet = named_session(&provider, "Quiet").await;
+    let busy = named_session(&provider, "Busy").await;
+
+    let mut message_ids = Vec::new();
+    for (content, tokens) in [("Hi", Some(10)), ("Hello", None), ("Gone", Some(7)), ("x".repeat(200).as_str(), Some(5))] {
+        let message = provider
+            .save_message(CreateMessage {
+                session_id: busy,
+                role: MessageRole::User,
+                content: content.to_string(),
+                embedding: None,
+                recall_score: None,
+                parent_id: None,
+                token_count: tokens,
+                content_parts: None,
+                reasoning_tokens: None,
+                generation_params: None,
+            })
+            .await
+            .unwrap();
+        message_ids.push(message.id);
+    }
+    assert!(provider.delete_message(message_ids[2]).await.unwrap());
+    sqlx::query("UPDATE message SET ts_ms = 5000 WHERE id = ?")
+        .bind(message_ids[3])
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    sqlx::query("UPDATE message SET ts_ms = id WHERE id <> ?")
+        .bind(message_ids[3])
+        .execute(provider.pool())
+        .await
+        .unwrap();
+
+    let summaries = provider.session_summaries(SessionQuery::default()).await.unwrap();
+    let summary_ids: Vec<i64> = summaries.iter().map(|summary| summary.session.id).collect();
+    assert_eq!(summary_ids, vec![quiet, busy], "a session without messages sorts by its creation time");
+
+    let busy_summary = &summaries[1];
+    assert_eq!(busy_summary.message_count, 3);
+    assert_eq!(busy_summary.total_tokens, 15);
+    assert_eq!(busy_summary.last_message_at, Some(5000));
+    assert_eq!(busy_summary.last_message_preview.as_deref(), Some("x".repeat(160).as_str()));
+
+    let quiet_summary = &summaries[0];
+    assert_eq!(quiet_summary.message_count, 0);
+    assert_eq!(quiet_summary.total_tokens, 0);
+    assert_eq!(quiet_summary.last_message_at, None);
+    assert_eq!(quiet_summary.last_message_preview, None);
+}
+
+// === Branches ===
+
+#[tokio::test]
+async fn switching_branches_moves_the_active_leaf() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+    let question = add_message(&provider, session_id, "Question", None).await.id;
+    let first = add_message(&provider, session_id, "First answer", None).await.id;
+    let second = add_message(&provider, session_id, "Second answer", Some(question)).await.id;
+
+    // A new reply becomes the active leaf; siblings are listed oldest first
+    assert_eq!(active_leaf(&provider, session_id).await, Some(second));
+    assert_eq!(ids(&provider.list_branches(session_id, Some(question)).await.unwrap()), vec![first, second]);
+    assert_eq!(ids(&provider.list_branches(session_id, None).await.unwrap()), vec![question]);
+    assert_eq!(ids(&provider.recent_messages(session_id, None).await.unwrap()), vec![second, question]);
+
+    let session = provider.switch_branch(session_id, first).await.unwrap();
+    assert_eq!(session.active_leaf_id, Some(first));
+    assert_eq!(ids(&provider.recent_messages(session_id, None).await.unwrap()), vec![first, question]);
+
+    // New messages continue the active branch
+    let follow_up = add_message(&provider, session_id, "Follow-up", None).await.id;
+    assert_eq!(parent_of(&provider, follow_up).await, Some(first));
+    assert_eq!(ids(&provider.transcript(follow_up).await.unwrap()), vec![question, first, follow_up]);
+
+    // Switching to an inner message follows the newest reply down to a leaf
+    let session = provider.switch_branch(session_id, question).await.unwrap();
+    assert_eq!(session.active_leaf_id, Some(second));
+    let session = provider.switch_branch(session_id, first).await.unwrap();
+    assert_eq!(session.active_leaf_id, Some(follow_up));
+
+    // Every branch is kept; only the active one is returned as recent
+    assert_eq!(provider.session_messages(session_id).await.unwrap().len(), 4);
+    assert_eq!(provider.recent_messages(session_id, None).await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn switching_to_a_trashed_or_foreign_message_fails() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 2).await;
+    let (_, other_ids) = linear_session(&provider, 1).await;
+    assert!(provider.delete_message(message_ids[1]).await.unwrap());
+
+    assert!(provider.switch_branch(session_id, message_ids[1]).await.is_err());
+    assert!(provider.switch_branch(session_id, other_ids[0]).await.is_err());
+    assert!(provider.switch_branch(session_id, 9999).await.is_err());
+}
+
+// === Forks ===
+
+#[tokio::test]
+async fn fork_copies_the_transcript_up_to_the_fork_point() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 3).await;
+
+    let fork = provider.fork_session(session_id, message_ids[1]).await.unwrap();
+    assert_eq!(fork.forked_from_message_id, Some(message_ids[1]));
+
+    let copied = provider.session_messages(fork.id).await.unwrap();
+    let contents: Vec<&str> = copied.iter().map(|message| message.content.as_str()).collect();
+    assert_eq!(contents, vec!["Message 1", "Message 2"]);
+}
+
+#[tokio::test]
+async fn fork_rejects_a_trashed_or_foreign_fork_point() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 3).await;
+    let (other_session, other_ids) = linear_session(&provider, 1).await;
+    assert!(provider.delete_message(message_ids[2]).await.unwrap());
+
+    assert!(provider.fork_session(session_id, message_ids[2]).await.is_err());
+    assert!(provider.fork_session(session_id, other_ids[0]).await.is_err());
+    assert!(provider.fork_session(other_session, message_ids[1]).await.is_err());
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM session").await, 2);
+}
+
+// === Trash ===
+
+#[tokio::test]
+async fn purging_a_mid_tree_message_reparents_its_children() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 3).await;
+    let branch = add_message(&provider, session_id, "Branch", Some(message_ids[1])).await.id;
+    assert_eq!(active_leaf(&provider, session_id).await, Some(branch));
+
+    assert!(provider.delete_message(message_ids[1]).await.unwrap());
+    let report = provider.purge_trash(0).await.unwrap();
+    assert_eq!(report.messages_purged, 1);
+
+    assert_eq!(parent_of(&provider, message_ids[2]).await, Some(message_ids[0]));
+    assert_eq!(parent_of(&provider, branch).await, Some(message_ids[0]));
+    assert_eq!(active_leaf(&provider, session_id).await, Some(branch));
+
+    let transcript = provider.transcript(branch).await.unwrap();
+    assert_eq!(ids(&transcript), vec![message_ids[0], branch]);
+}
+
+#[tokio::test]
+async fn purging_the_active_leaf_and_its_parent_moves_the_leaf_up() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 3).await;
+    assert!(provider.delete_message(message_ids[2]).await.unwrap());
+    assert!(provider.delete_message(message_ids[1]).await.unwrap());
+
+    let report = provider.purge_trash(0).await.unwrap();
+    assert_eq!(report.messages_purged, 2);
+
+    let leaf = active_leaf(&provider, session_id).await;
+    assert_eq!(leaf, Some(message_ids[0]));
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 1);
+    let violations = sqlx::query("PRAGMA foreign_key_check")
+        .fetch_all(provider.pool())
+        .await
+        .unwrap();
+    assert!(violations.is_empty());
+}
+
+#[tokio::test]
+async fn purge_keeps_recently_trashed_messages() {
+    let provider = provider().await;
+    let (session_id, message_ids) = linear_session(&provider, 2).await;
+    assert!(provider.delete_message(message_ids[1]).await.unwrap());
+
+    let report = provider.purge_trash(60 * 60).await.unwrap();
+    assert_eq!(report.messages_purged, 0);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 2);
+    assert!(active_leaf(&provider, session_id).await.is_some());
+}
+
+// === Content parts ===
+
+#[tokio::test]
+async fn content_parts_round_trip_and_derive_plain_text() {
+    let parts = vec![
+        ContentPart::Text {
+            text: "Look at this".to_string(),
+        },
+        ContentPart::Image {
+            url: "https://example.com/chart.png".to_string(),
+            mime_type: Some("image/png".to_string()),
+            alt: None,
+        },
+        ContentPart::ToolCall {
+            id: "call-1".to_string(),
+            name: "calculate".to_string(),
+            arguments: serde_json::json!({ "expression": "6 * 7" }),
+        },
+        ContentPart::ToolResult {
+            tool_call_id: "call-1".to_string(),
+            content: "42".to_string(),
+            is_error: false,
+        },
+        ContentPart::Reasoning {
+            text: "Multiply".to_string(),
+        },
+    ];
+
+    let json = serde_json::to_value(&parts).unwrap();
+    let types: Vec<&str> = json.as_array().unwrap().iter().map(|part| part["type"].as_str().unwrap()).collect();
+    assert_eq!(types, vec!["text", "image", "tool_call", "tool_result", "reasoning"]);
+    assert_eq!(serde_json::from_value::<Vec<ContentPart>>(json).unwrap(), parts);
+    let without_flag: ContentPart =
+        serde_json::from_value(serde_json::json!({ "type": "tool_result", "tool_call_id": "call-1", "content": "42" })).unwrap();
+    assert_eq!(without_flag, parts[3]);
+    assert!(serde_json::from_value::<ContentPart>(serde_json::json!({ "type": "video", "url": "x" })).is_err());
+
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+    let saved = provider
+        .save_message(CreateMessage {
+            session_id,
+            role: MessageRole::Assistant,
+            content: "ignored".to_string(),
+            embedding: None,
+            recall_score: None,
+            parent_id: None,
+            token_count: None,
+            content_parts: Some(parts.clone()),
+            reasoning_tokens: None,
+            generation_params: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(saved.content, "Look at this\n\n42");
+    let stored = provider.session_messages(session_id).await.unwrap();
+    assert_eq!(stored[0].content_parts.as_ref(), Some(&parts));
+
+    // Plain text replaces the parts, which are kept on the revision
+    let edited = provider
+        .update_message(saved.id, UpdateMessage {
+            content: Some("Plain".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(edited.content_parts, None);
+    let revisions = provider.list_message_revisions(saved.id).await.unwrap();
+    assert_eq!(revisions[0].content_parts.as_ref(), Some(&parts));
+
+    let restored = provider.restore_message_revision(revisions[0].id).await.unwrap();
+    assert_eq!(restored.content, "Look at this\n\n42");
+    assert_eq!(restored.content_parts, Some(parts));
+}
+
+// === Session status ===
+
+#[tokio::test]
+async fn closed_and_archived_sessions_reject_message_changes() {
+    let provider = provider().await;
+    for status in [SessionStatus::Closed, SessionStatus::Archived] {
+        let (session_id, message_ids) = linear_session(&provider, 1).await;
+        provider.set_session_status(session_id, status).await.unwrap();
+
+        let read_only = |result: Result<(), DatabaseError>| {
+            assert!(
+                matches!(result, Err(DatabaseError::SessionReadOnly { session_id: id, status: s }) if id == session_id && s == status),
+                "{} session accepted a change",
+                status
+            )
+        };
+        let saved = provider
+            .save_message(CreateMessage {
+                session_id,
+                role: MessageRole::User,
+                content: "Late".to_string(),
+                embedding: None,
+                recall_score: None,
+                parent_id: None,
+                token_count: None,
+                content_parts: None,
+                reasoning_tokens: None,
+                generation_params: None,
+            })
+            .await;
+        read_only(saved.map(|_| ()));
+        let edited = provider
+            .update_message(message_ids[0], UpdateMessage {
+                content: Some("Edited".to_string()),
+                ..Default::default()
+            })
+            .await;
+        read_only(edited.map(|_| ()));
+        read_only(provider.delete_message(message_ids[0]).await.map(|_| ()));
+
+        // The session itself can still be renamed, and reopening makes it writable again
+        provider
+            .update_session(session_id, UpdateSession {
+                name: Some("Renamed".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        provider.set_session_status(session_id, SessionStatus::Open).await.unwrap();
+        assert!(provider.delete_message(message_ids[0]).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn invalid_status_transitions_are_rejected() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+    provider.set_session_status(session_id, SessionStatus::Archived).await.unwrap();
+
+    let result = provider.set_session_status(session_id, SessionStatus::Closed).await;
+    assert!(matches!(
+        result,
+        Err(DatabaseError::InvalidStatusTransition {
+            from: SessionStatus::Archived,
+            to: SessionStatus::Closed,
+        })
+    ));
+    assert_eq!(provider.get_session_by_id(session_id).await.unwrap().status, SessionStatus::Archived);
+
+    // Setting the current status again is allowed
+    provider.set_session_status(session_id, SessionStatus::Archived).await.unwrap();
+}
+
+// === Generation parameters ===
+
+#[test]
+fn generation_params_reject_reserved_extras_and_out_of_range_values() {
+    let extras = |key: &str| {
+        let mut extras = serde_json::Map::new();
+        extras.insert(key.to_string(), serde_json::json!(1));
+        Some(extras)
+    };
+    let invalid = [
+        GenerationParams { temperature: Some(-0.1), ..Default::default() },
+        GenerationParams { temperature: Some(2.1), ..Default::default() },
+        GenerationParams { top_p: Some(0.0), ..Default::default() },
+        GenerationParams { top_p: Some(1.5), ..Default::default() },
+        GenerationParams { max_tokens: Some(0), ..Default::default() },
+        GenerationParams { stop: Some(vec!["a".into(), "b".into(), "c".into(), "d".into(), "e".into()]), ..Default::default() },
+        GenerationParams { stop: Some(vec![String::new()]), ..Default::default() },
+        GenerationParams { extras: extras("model"), ..Default::default() },
+        GenerationParams { extras: extras("temperature"), ..Default::default() },
+        GenerationParams { extras: extras("stream"), ..Default::default() },
+    ];
+    for params in invalid {
+        assert!(
+            matches!(params.validate(), Err(DatabaseError::InvalidGenerationParams(_))),
+            "{:?} should be rejected",
+            params
+        );
+    }
+
+    let valid = GenerationParams {
+        temperature: Some(2.0),
+        top_p: Some(1.0),
+        max_tokens: Some(1),
+        stop: Some(vec!["a".into(), "b".into(), "c".into(), "d".into()]),
+        seed: Some(7),
+        extras: extras("top_k"),
+    };
+    valid.validate().unwrap();
+    GenerationParams { temperature: Some(0.0), ..Default::default() }.validate().unwrap();
+}
+
+#[test]
+fn model_limits_reject_unsupported_parameters_and_excess_max_tokens() {
+    let params = GenerationParams { temperature: Some(0.5), max_tokens: Some(5000), ..Default::default() };
+
+    // Nothing reported, nothing to check
+    ModelLimits::default().check("m", &params).unwrap();
+
+    let limits = ModelLimits {
+        supported_parameters: Some(vec!["max_tokens".to_string()]),
+        ..Default::default()
+    };
+    let error = limits.check("m", &params).unwrap_err();
+    assert!(matches!(&error, DatabaseError::InvalidGenerationParams(message) if message.contains("temperature")));
+
+    // The output limit takes precedence over the context length
+    let limits = ModelLimits { context_length: Some(100_000), max_completion_tokens: Some(4096), ..Default::default() };
+    assert!(matches!(limits.check("m", &params), Err(DatabaseError::InvalidGenerationParams(_))));
+    let limits = ModelLimits { context_length: Some(4096), ..Default::default() };
+    assert!(matches!(limits.check("m", &params), Err(DatabaseError::InvalidGenerationParams(_))));
+    let limits = ModelLimits {
+        context_length: Some(100_000),
+        supported_parameters: Some(vec!["temperature".to_string(), "max_tokens".to_string()]),
+        ..Default::default()
+    };
+    limits.check("m", &params).unwrap();
+}
+
+#[test]
+fn chat_request_standard_fields_override_extras() {
+    let mut extras = serde_json::Map::new();
+    extras.insert("temperature".to_string(), serde_json::json!(1.5));
+    extras.insert("model".to_string(), serde_json::json!("other"));
+    extras.insert("top_k".to_string(), serde_json::json!(40));
+    let params = GenerationParams { temperature: Some(0.2), seed: Some(3), extras: Some(extras), ..Default::default() };
+
+    let body = chat_request("m", vec![serde_json::json!({ "role": "user", "content": "hi" })], &params);
+    assert_eq!(body["temperature"], serde_json::json!(0.2));
+    assert_eq!(body["seed"], serde_json::json!(3));
+    assert_eq!(body["top_k"], serde_json::json!(40));
+    assert_eq!(body["messages"][0]["content"], "hi");
+    // Fields without a standard value still come from the extras; validation
+    // is what keeps those out
+    assert_eq!(body["model"], "other");
+    assert!(body.get("top_p").is_none());
+}
+
+// === Imports ===
+
+/// Content and parent of every message in a session, in insertion order
+async fn message_tree(provider: &SqliteProvider, session_id: i64) -> Vec<(i64, String, Option<i64>)> {
+    sqlx::query_as("SELECT id, content, parent_id FROM message WHERE session_id = ? ORDER BY id")
+        .bind(session_id)
+        .fetch_all(provider.pool())
+        .await
+        .unwrap()
+}
+
+fn chatgpt_node(id: &str, parent: Option<&str>, children: &[&str], role: &str, text: &str, time: f64) -> serde_json::Value {
+    serde_json::json!({
+        "id": id,
+        "parent": parent,
+        "children": children,
+        "message": {
+            "id": id,
+            "author": { "role": role },
+            "create_time": time,
+            "content": { "content_type": "text", "parts": [text] },
+            "metadata": { "model_slug": "gpt-4o" },
+        },
+    })
+}
+
+#[tokio::test]
+async fn chatgpt_import_keeps_every_branch_and_skips_a_second_import() {
+    let provider = provider().await;
+    let dir = tempfile::tempdir().unwrap();
+    // A regenerated answer: "Hi" and "Hey" both answer "Hello", and the
+    // conversation continued from "Hey"
+    let archive = serde_json::json!([{
+        "conversation_id": "conv-1",
+        "title": "Greetings",
+        "create_time": 1_700_000_000.0,
+        "current_node": "u2",
+        "mapping": {
+            "root": { "id": "root", "parent": null, "children": ["u1"], "message": null },
+            "u1": chatgpt_node("u1", Some("root"), &["a1", "a2"], "user", "Hello", 1_700_000_001.0),
+            "a1": chatgpt_node("a1", Some("u1"), &[], "assistant", "Hi", 1_700_000_002.0),
+            "a2": chatgpt_node("a2", Some("u1"), &["u2"], "assistant", "Hey", 1_700_000_003.0),
+            "u2": chatgpt_node("u2", Some("a2"), &[], "user", "How are you?", 1_700_000_004.0),
+        },
+    }]);
+    write_json(&dir.path().join("conversations.json"), &archive);
+
+    let report = import_conversations(&provider, ImportSource::ChatGpt, dir.path(), |_| {}).await.unwrap();
+    assert_eq!((report.imported, report.skipped, report.messages), (1, 0, 4));
+
+    let session_id = report.session_ids[0];
+    let session = provider.get_session_by_id(session_id).await.unwrap();
+    assert_eq!(session.name, "Greetings");
+    assert_eq!(session.model_id.as_deref(), Some("gpt-4o"));
+    assert_eq!(session.created_at, 1_700_000_000);
+
+    let tree = message_tree(&provider, session_id).await;
+    let id = |content: &str| tree.iter().find(|(_, text, _)| text == content).unwrap().0;
+    let parent = |content: &str| tree.iter().find(|(_, text, _)| text == content).unwrap().2;
+    assert_eq!(tree.len(), 4);
+    assert_eq!(parent("Hello"), None);
+    assert_eq!(parent("Hi"), Some(id("Hello")));
+    assert_eq!(parent("Hey"), Some(id("Hello")));
+    assert_eq!(parent("How are you?"), Some(id("Hey")));
+    assert_eq!(active_leaf(&provider, session_id).await, Some(id("How are you?")));
+
+    // The same archive again is recognised by its source and conversation id
+    let again = import_conversations(&provider, ImportSource::ChatGpt, dir.path(), |_| {}).await.unwrap();
+    assert_eq!((again.imported, again.skipped, again.messages), (0, 1, 0));
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM session").await, 1);
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 4);
+    let (source, import_id): (String, String) =
+        sqlx::query_as("SELECT import_source, import_id FROM session WHERE id = ?")
+            .bind(session_id)
+            .fetch_one(provider.pool())
+            .await
+            .unwrap();
+    assert_eq!((source.as_str(), import_id.as_str()), ("chatgpt", "conv-1"));
+}
+
+#[tokio::test]
+async fn claude_import_without_parent_ids_is_linear() {
+    let provider = provider().await;
+    let dir = tempfile::tempdir().unwrap();
+    let message = |uuid: &str, sender: &str, text: &str, parent: Option<&str>| {
+        let mut message = serde_json::json!({
+            "uuid": uuid,
+            "sender": sender,
+            "text": text,
+            "created_at": "2024-05-01T10:00:00Z",
+        });
+        if let Some(parent) = parent {
+            message["parent_message_uuid"] = serde_json::json!(parent);
+        }
+        message
+    };
+    // An older export without parent ids, ending with a parent the archive doesn't contain
+    let archive = serde_json::json!([{
+        "uuid": "claude-1",
+        "name": "",
+        "created_at": "2024-05-01T09:59:00Z",
+        "chat_messages": [
+            message("m1", "human", "Question", None),
+            message("m2", "assistant", "Answer", None),
+            message("m3", "human", "Follow-up", None),
+            message("m4", "assistant", "More", Some("missing")),
+        ],
+    }]);
+    let path = dir.path().join("claude.json");
+    write_json(&path, &archive);
+
+    let report = import_conversations(&provider, ImportSource::Claude, &path, |_| {}).await.unwrap();
+    assert_eq!((report.imported, report.messages), (1, 4));
+
+    let session_id = report.session_ids[0];
+    let session = provider.get_session_by_id(session_id).await.unwrap();
+    assert_eq!(session.name, "Imported conversation");
+    assert_eq!(session.llm_provider.as_deref(), Some("anthropic"));
+
+    let tree = message_tree(&provider, session_id).await;
+    let contents: Vec<_> = tree.iter().map(|(_, text, _)| text.as_str()).collect();
+    assert_eq!(contents, ["Question", "Answer", "Follow-up", "More"]);
+    let parents: Vec<_> = tree.iter().map(|(_, _, parent)| *parent).collect();
+    assert_eq!(parents, [None, Some(tree[0].0), Some(tree[1].0), Some(tree[2].0)]);
+    assert_eq!(active_leaf(&provider, session_id).await, Some(tree[3].0));
+
+    let again = import_conversations(&provider, ImportSource::Claude, &path, |_| {}).await.unwrap();
+    assert_eq!((again.imported, again.skipped), (0, 1));
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 4);
+}
+
+// === Backups ===
+
+#[test]
+fn rotation_keeps_the_newest_daily_and_weekly_snapshots_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let touch = |name: String| {
+        std::fs::write(dir.path().join(&name), b"snapshot").unwrap();
+        name
+    };
+    let daily: Vec<_> = (1..=10).map(|day| touch(format!("conv-daily-202405{:02}T030000Z.db", day))).collect();
+    let weekly: Vec<_> = (1..=6).map(|week| touch(format!("conv-weekly-2024{:02}01T030000Z.db", week))).collect();
+    let kept = [
+        touch("conv-manual-20240101T000000Z.db".to_string()),
+        touch("conv-pre_restore-20240102T000000Z.db".to_string()),
+        touch("notes.txt".to_string()),
+        touch("conv-daily-garbled.db".to_string()),
+    ];
+
+    let removed = rotate_backups(dir.path(), &BackupSchedule::default()).unwrap();
+    let mut removed: Vec<_> = removed.iter().map(|path| path.file_name().unwrap().to_string_lossy().to_string()).collect();
+    removed.sort();
+    let mut expected: Vec<_> = daily[..3].iter().chain(&weekly[..2]).cloned().collect();
+    expected.sort();
+    assert_eq!(removed, expected);
+
+    let exists = |name: &String| dir.path().join(name).exists();
+    assert!(daily[3..].iter().all(exists));
+    assert!(weekly[2..].iter().all(exists));
+    assert!(kept.iter().all(exists));
+    assert!(!daily[..3].iter().any(exists));
+
+    // Nothing more to drop once within retention
+    assert!(rotate_backups(dir.path(), &BackupSchedule::default()).unwrap().is_empty());
+    let none = BackupSchedule { daily: 0, weekly: 0, ..Default::default() };
+    assert_eq!(rotate_backups(dir.path(), &none).unwrap().len(), 11);
+    assert!(kept.iter().all(exists));
+}
+
+#[tokio::test]
+async fn restore_swaps_in_the_backup_and_drops_stale_sidecars() {
+    let dir = tempfile::tempdir().unwrap();
+    let backup = dir.path().join("backup.db");
+    let db_path = dir.path().join("conv.db");
+
+    let source = SqliteProvider::new(&backup.to_string_lossy()).await.unwrap();
+    source.migrate().await.unwrap();
+    let session_id = new_session(&source).await;
+    add_message(&source, session_id, "From the backup", None).await;
+    source.close().await;
+    encryption::remove_sidecars(&backup).unwrap();
+
+    // Only replaced, so it needn't be a real database
+    std::fs::write(&db_path, b"current").unwrap();
+    let wal = dir.path().join("conv.db-wal");
+    let shm = dir.path().join("conv.db-shm");
+    std::fs::write(&wal, b"stale").unwrap();
+    std::fs::write(&shm, b"stale").unwrap();
+
+    // A file that fails verification leaves everything in place
+    let corrupt = dir.path().join("corrupt.db");
+    std::fs::write(&corrupt, b"not a database").unwrap();
+    assert!(restore_backup(&corrupt, &db_path, None, None).await.is_err());
+    assert!(wal.exists() && shm.exists());
+    assert_eq!(std::fs::read(&db_path).unwrap(), b"current");
+
+    restore_backup(&backup, &db_path, None, None).await.unwrap();
+    assert!(!wal.exists());
+    assert!(!shm.exists());
+    assert!(!dir.path().join("conv.restoring").exists());
+    assert!(backup.exists(), "the backup itself is kept");
+
+    let restored = SqliteProvider::new(&db_path.to_string_lossy()).await.unwrap();
+    let messages = restored.session_messages(session_id).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].content, "From the backup");
+    restored.close().await;
+}
+
+// === Encryption ===
+
+/// A migrated file database at `path` holding one session with one message
+async fn seeded_database(path: &Path) -> i64 {
+    let provider = SqliteProvider::new(&path.to_string_lossy()).await.unwrap();
+    provider.migrate().await.unwrap();
+    let session_id = new_session(&provider).await;
+    add_message(&provider, session_id, "Secret", None).await;
+    provider.close().await;
+    encryption::remove_sidecars(path).unwrap();
+    session_id
+}
+
+async fn open_with(path: &Path, passphrase: Option<&str>) -> crate::database::Result<SqliteProvider> {
+    SqliteProvider::open(&path.to_string_lossy(), passphrase, &PoolOptions::default()).await
+}
+
+#[tokio::test]
+async fn encryption_locks_the_file_until_the_passphrase_is_given() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("conv.db");
+
+    assert!(!encryption::is_encrypted(&path).unwrap(), "a missing file is not encrypted");
+    std::fs::write(&path, b"").unwrap();
+    assert!(!encryption::is_encrypted(&path).unwrap(), "an empty file is a new database");
+    std::fs::remove_file(&path).unwrap();
+
+    let session_id = seeded_database(&path).await;
+    assert!(!encryption::is_encrypted(&path).unwrap());
+
+    encryption::encrypt_database(&path, "correct horse").await.unwrap();
+    assert!(encryption::is_encrypted(&path).unwrap());
+    assert!(!std::fs::read(&path).unwrap().windows(6).any(|bytes| bytes == b"Secret"));
+    assert!(!dir.path().join("conv.encrypting").exists());
+    assert!(encryption::encrypt_database(&path, "again").await.is_err());
+
+    assert!(matches!(open_with(&path, None).await, Err(DatabaseError::Locked)));
+    assert!(matches!(open_with(&path, Some("wrong")).await, Err(DatabaseError::WrongPassphrase)));
+    let provider = open_with(&path, Some("correct horse")).await.unwrap();
+    assert_eq!(provider.session_messages(session_id).await.unwrap()[0].content, "Secret");
+    provider.close().await;
+}
+
+#[tokio::test]
+async fn changing_the_passphrase_retires_the_old_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("conv.db");
+    let session_id = seeded_database(&path).await;
+    encryption::encrypt_database(&path, "old").await.unwrap();
+
+    assert!(matches!(
+        encryption::change_passphrase(&path, "wrong", "new").await,
+        Err(DatabaseError::WrongPassphrase)
+    ));
+    encryption::change_passphrase(&path, "old", "new").await.unwrap();
+    encryption::remove_sidecars(&path).unwrap();
+
+    assert!(matches!(open_with(&path, Some("old")).await, Err(DatabaseError::WrongPassphrase)));
+    let provider = open_with(&path, Some("new")).await.unwrap();
+    assert_eq!(provider.session_messages(session_id).await.unwrap().len(), 1);
+    provider.close().await;
+}
+
+#[tokio::test]
+async fn backups_follow_the_database_passphrase() {
+    let dir = tempfile::tempdir().unwrap();
+    let backups = dir.path().join("backups");
+    let plaintext = backups.join("conv-manual-20240101T000000Z.db");
+    let current = backups.join("conv-daily-20240102T000000Z.db");
+    let older = backups.join("conv-weekly-20240103T000000Z.db");
+    let session_id = seeded_database(&plaintext).await;
+    seeded_database(&current).await;
+    seeded_database(&older).await;
+    encryption::encrypt_database(&current, "current").await.unwrap();
+    encryption::encrypt_database(&older, "older").await.unwrap();
+
+    let report = rekey_backups(&backups, Some("current"), "new").await.unwrap();
+    assert_eq!(report.rekeyed, 2);
+    assert_eq!(report.skipped, std::slice::from_ref(&older));
+    for path in [&plaintext, &current] {
+        assert!(encryption::is_encrypted(path).unwrap());
+        open_with(path, Some("new")).await.unwrap().close().await;
+    }
+    open_with(&older, Some("older")).await.unwrap().close().await;
+
+    // A backup under an older passphrase is opened with it and comes back under the current one
+    let db_path = dir.path().join("conv.db");
+    std::fs::write(&db_path, b"current").unwrap();
+    assert!(restore_backup(&older, &db_path, Some("new"), Some("new")).await.is_err());
+    restore_backup(&older, &db_path, Some("older"), Some("new")).await.unwrap();
+    assert!(matches!(open_with(&db_path, Some("older")).await, Err(DatabaseError::WrongPassphrase)));
+    let provider = open_with(&db_path, Some("new")).await.unwrap();
+    assert_eq!(provider.session_messages(session_id).await.unwrap().len(), 1);
+    provider.close().await;
+}
+
+// === Maintenance ===
+
+#[tokio::test]
+async fn stats_report_file_and_page_sizes() {
+    let provider = provider().await;
+    let (session_id, _) = linear_session(&provider, 3).await;
+    new_session(&provider).await;
+    provider.delete_session(session_id).await.unwrap();
+
+    let stats = provider.get_database_stats().await.unwrap();
+    assert_eq!((stats.session_count, stats.message_count), (1, 0));
+    // Row counts include the trash
+    assert_eq!(stats.table_row_counts["session"], 2);
+    assert_eq!(stats.table_row_counts["message"], 3);
+    // In memory the size comes from the pages, and there is no WAL
+    assert_eq!(stats.database_size_bytes, Some(stats.page_size * stats.page_count));
+    assert_eq!(stats.wal_size_bytes, None);
+    assert_eq!(stats.reclaimable_bytes, stats.freelist_count * stats.page_size);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("conv.db");
+    let provider = SqliteProvider::new(&path.to_string_lossy()).await.unwrap();
+    provider.migrate().await.unwrap();
+    linear_session(&provider, 3).await;
+
+    let stats = provider.get_database_stats().await.unwrap();
+    assert_eq!(stats.database_size_bytes, Some(std::fs::metadata(&path).unwrap().len() as i64));
+    let wal = std::fs::metadata(dir.path().join("conv.db-wal")).unwrap().len() as i64;
+    assert_eq!(stats.wal_size_bytes, Some(wal));
+    provider.close().await;
+}
+
+#[tokio::test]
+async fn maintenance_reclaims_purged_space_and_empties_the_wal() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("conv.db");
+    let database = SqliteProvider::new(&path.to_string_lossy()).await.unwrap();
+    database.migrate().await.unwrap();
+
+    let session_id = new_session(&database).await;
+    let long = "x".repeat(16 * 1024);
+    for _ in 0..50 {
+        add_message(&database, session_id, &long, None).await;
+    }
+    let checkpoint = database.checkpoint_wal(CheckpointMode::Truncate).await.unwrap();
+    assert!(!checkpoint.busy);
+    assert!(checkpoint.wal_size_before_bytes.unwrap() > 0);
+    assert_eq!(checkpoint.wal_size_after_bytes, Some(0));
+    assert_eq!(checkpoint.checkpointed_frames, checkpoint.wal_frames);
+
+    database.delete_session(session_id).await.unwrap();
+    database.purge_trash(0).await.unwrap();
+    database.checkpoint_wal(CheckpointMode::Truncate).await.unwrap();
+    let stats = database.get_database_stats().await.unwrap();
+    assert!(stats.freelist_count > 0, "purged pages stay on the freelist");
+
+    let vacuum = database.vacuum().await.unwrap();
+    assert!(vacuum.reclaimed_bytes >= stats.reclaimable_bytes / 2);
+    assert_eq!(vacuum.size_before_bytes - vacuum.size_after_bytes, vacuum.reclaimed_bytes);
+    assert_eq!(vacuum.size_after_bytes, std::fs::metadata(&path).unwrap().len() as i64);
+    assert_eq!(database.get_database_stats().await.unwrap().freelist_count, 0);
+
+    let integrity = database.integrity_check().await.unwrap();
+    assert!(integrity.ok);
+    assert!(integrity.problems.is_empty());
+    assert_eq!(integrity.foreign_key_violations, 0);
+
+    new_session(&database).await;
+    assert!(database.analyze().await.unwrap().tables_analyzed > 0);
+    database.close().await;
+
+    // Without a WAL there is nothing to checkpoint
+    let checkpoint = provider().await.checkpoint_wal(CheckpointMode::Passive).await.unwrap();
+    assert_eq!(checkpoint.wal_frames, -1);
+    assert_eq!(checkpoint.wal_size_before_bytes, None);
+}
+
+#[tokio::test]
+async fn integrity_check_reports_foreign_key_violations() {
+    let provider = provider().await;
+    let session_id = new_session(&provider).await;
+    add_message(&provider, session_id, "Orphan", None).await;
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(provider.pool()).await.unwrap();
+    sqlx::query("DELETE FROM session").execute(provider.pool()).await.unwrap();
+
+    let report = provider.integrity_check().await.unwrap();
+    assert!(!report.ok);
+    assert!(report.problems.is_empty());
+    assert_eq!(report.foreign_key_violations, 1);
+}
+
+// === Export ===
+
+#[test]
+fn html_export_inlines_attachments_only_and_drops_unsafe_sources() {
+    let _env = crate::paths::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = tempfile::tempdir().unwrap();
+    let _dirs = IsolatedDirs::new(root.path());
+
+    let attachments = crate::workspaces::active_workspace().attachments_dir();
+    std::fs::create_dir_all(&attachments).unwrap();
+    let image = attachments.join("chart.png");
+    std::fs::write(&image, b"not really a png").unwrap();
+    // Outside the attachments directory, reached directly and through `..`
+    let outside = root.path().join("secret.png");
+    std::fs::write(&outside, b"private").unwrap();
+    std::fs::write(attachments.parent().unwrap().join("secret.png"), b"private").unwrap();
+
+    let image_part = |url: &str| ContentPart::Image {
+        url: url.to_string(),
+        mime_type: None,
+        alt: Some("chart".to_string()),
+    };
+    let content_parts = vec![
+        image_part(&image.to_string_lossy()),
+        image_part(&format!("file://{}", image.display())),
+        image_part("chart.png"),
+        image_part(&outside.to_string_lossy()),
+        image_part(&format!("file://{}", outside.display())),
+        image_part("../secret.png"),
+        image_part("javascript:alert(1)"),
+        image_part("JavaScript:alert(2)"),
+        image_part("http://example.com/tracker.png"),
+        image_part("https://example.com/photo.png"),
+        image_part("data:image/png;base64,AAAA"),
+        image_part("/nonexistent/missing.png"),
+    ];
+
+    let html = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let provider = provider().await;
+            let session_id = new_session(&provider).await;
+            provider
+                .save_message(CreateMessage {
+                    session_id,
+                    role: MessageRole::User,
+                    content: String::new(),
+                    embedding: None,
+                    recall_score: None,
+                    parent_id: None,
+                    token_count: None,
+                    content_parts: Some(content_parts),
+                    reasoning_tokens: None,
+                    generation_params: None,
+                })
+                .await
+                .unwrap();
+            export_session(&provider, session_id, ExportFormat::Html).await.unwrap()
+        });
+
+    let inlined = "src=\"data:image/png;base64,bm90IHJlYWxseSBhIHBuZw==\"";
+    assert_eq!(html.matches(inlined).count(), 3);
+    assert!(!html.contains("cHJpdmF0ZQ=="), "a file outside the attachments was inlined");
+    assert!(html.contains("src=\"https://example.com/photo.png\""));
+    assert!(html.contains("src=\"data:image/png;base64,AAAA\""));
+    assert!(!html.to_lowercase().contains("javascript:"));
+    assert!(!html.contains("http://example.com"));
+    assert!(!html.contains(&root.path().to_string_lossy().to_string()));
+    assert_eq!(html.matches("[Image not included: chart]").count(), 7);
+}
+
+// === Concurrency ===
+
+#[tokio::test]
+async fn pool_connections_use_wal_foreign_keys_and_the_configured_timeout() {
+    let options = PoolOptions::from_memory_config(&serde_json::json!({
+        "provider": "sqlite",
+        "config": { "max_connections": 2, "busy_timeout_ms": 1234 },
+    }));
+    assert_eq!((options.max_connections, options.min_connections, options.busy_timeout_ms), (2, 0, 1234));
+    let invalid = PoolOptions::from_memory_config(&serde_json::json!({ "config": { "max_connections": "many" } }));
+    assert_eq!(invalid.max_connections, PoolOptions::default().max_connections);
+
+    let dir = tempfile::tempdir().unwrap();
+    let provider = SqliteProvider::open(&dir.path().join("conv.db").to_string_lossy(), None, &options)
+        .await
+        .unwrap();
+    provider.migrate().await.unwrap();
+    let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(provider.pool()).await.unwrap();
+    assert_eq!(journal_mode, "wal");
+    assert_eq!(count(&provider, "PRAGMA foreign_keys").await, 1);
+    assert_eq!(count(&provider, "PRAGMA synchronous").await, 1); // NORMAL
+    assert_eq!(count(&provider, "PRAGMA busy_timeout").await, 1234);
+
+    // Deleting a session cascades to its messages
+    let (session_id, _) = linear_session(&provider, 2).await;
+    sqlx::query("DELETE FROM session WHERE id = ?")
+        .bind(session_id)
+        .execute(provider.pool())
+        .await
+        .unwrap();
+    assert_eq!(count(&provider, "SELECT COUNT(*) FROM message").await, 0);
+    provider.close().await;
+}
+
+#[tokio::test]
+async fn reads_are_not_blocked_by_a_long_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = DatabaseManager::new(DatabaseConfig {
+        provider: DatabaseProvider::SQLite,
+        connection_string: dir.path().join("conv.db").to_string_lossy().to_string(),
+        passphrase: None,
+        pool: Default::default(),
+    })
+    .await
+    .unwrap();
+    manager.migrate().await.unwrap();
+    let (session_id, _) = linear_session(&manager.provider, 3).await;
+    let state: DatabaseState = Arc::new(RwLock::new(Some(manager.clone())));
+
+    // A writer holds its transaction open for the whole test, as a long import would
+    let mut writer = manager.provider.pool().begin().await.unwrap();
+    sqlx::query("INSERT INTO session (name) VALUES ('Uncommitted')")
+        .execute(&mut *writer)
+        .await
+        .unwrap();
+
+    let started = Instant::now();
+    let mut reads = 0;
+    let mut slowest = Duration::ZERO;
+    while started.elapsed() < Duration::from_millis(500) {
+        let read_started = Instant::now();
+        let reader = database(&state).await.unwrap();
+        let repo = reader.memory_repo();
+        let (sessions, messages) = tokio::time::timeout(Duration::from_secs(2), async {
+            (
+                repo.get_sessions(SessionQuery::default()).await.unwrap(),
+                repo.recent_messages(session_id, None).await.unwrap(),
+            )
+        })
+        .await
+        .expect("read blocked by the open write transaction");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(messages.len(), 3);
+        slowest = slowest.max(read_started.elapsed());
+        reads += 1;
+    }
+    writer.commit().await.unwrap();
+
+    assert!(reads >= 10, "only {} reads completed during the write", reads);
+    assert!(slowest < Duration::from_millis(250), "slowest read took {:?}", slowest);
+    assert_eq!(manager.memory_repo().get_sessions(SessionQuery::default()).await.unwrap().len(), 2);
+}
@@ -9,11 +9,14 @@ use tauri::{
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 
 mod database;
 pub mod connectors;
 mod settings;
+mod chat;
+mod workspaces;
+mod paths;
 
 use database::commands::DatabaseState;
 
@@ -51,15 +54,7 @@ fn hide_window(app: tauri::AppHandle) -> Result<(), String> {
 #[tauri::command]
 async fn read_settings_file(file_path: String) -> Result<String, String> {
     // Expand the tilde path
-    let expanded_path = if file_path.starts_with("~/") {
-        if let Ok(home_dir) = std::env::var("HOME") {
-            file_path.replace("~/", &format!("{}/", home_dir))
-        } else {
-            return Err("Unable to determine home directory".to_string());
-        }
-    } else {
-        file_path
-    };
+    let expanded_path = paths::expand_tilde(&file_path).to_string_lossy().to_string();
 
     match fs::read_to_string(&expanded_path) {
         Ok(content) => Ok(content),
@@ -70,15 +65,7 @@ async fn read_settings_file(file_path: String) -> Result<String, String> {
 #[tauri::command]
 async fn write_settings_file(file_path: String, content: String) -> Result<(), String> {
     // Expand the tilde path
-    let expanded_path = if file_path.starts_with("~/") {
-        if let Ok(home_dir) = std::env::var("HOME") {
-            file_path.replace("~/", &format!("{}/", home_dir))
-        } else {
-            return Err("Unable to determine home directory".to_string());
-        }
-    } else {
-        file_path
-    };
+    let expanded_path = paths::expand_tilde(&file_path).to_string_lossy().to_string();
 
     // Create parent directories if they don't exist
     if let Some(parent) = Path::new(&expanded_path).parent() {
@@ -106,18 +93,71 @@ pub fn run() {
             write_settings_file,
             // Database commands
             database::commands::init_database,
+            database::commands::get_database_status,
+            database::commands::unlock_database,
+            database::commands::encrypt_database,
+            database::commands::change_database_passphrase,
             database::commands::get_database_path,
             database::commands::get_database_stats,
             database::commands::clear_all_memory,
+            // Maintenance commands
+            database::commands::check_database_integrity,
+            database::commands::vacuum_database,
+            database::commands::analyze_database,
+            database::commands::checkpoint_database,
+            // Backup commands
+            database::commands::create_backup,
+            database::commands::list_backups,
+            database::commands::restore_backup,
             // Session commands
             database::commands::create_session,
             database::commands::get_sessions,
             database::commands::get_session_by_id,
+            database::commands::update_session,
+            database::commands::fork_session,
             database::commands::delete_session,
+            // Session organisation commands
+            database::commands::create_folder,
+            database::commands::list_folders,
+            database::commands::delete_folder,
+            database::commands::set_session_folder,
+            database::commands::set_session_tags,
+            database::commands::list_tags,
+            database::commands::set_session_pinned,
+            database::commands::set_session_archived,
+            database::commands::close_session,
+            database::commands::reopen_session,
+            // Export commands
+            database::commands::export_session,
+            database::commands::export_sessions,
+            // Import commands
+            database::commands::import_conversations,
+            // Persona commands
+            database::commands::create_persona,
+            database::commands::list_personas,
+            database::commands::update_persona,
+            database::commands::delete_persona,
+            database::commands::import_personas,
+            database::commands::export_personas,
             // Message commands
             database::commands::save_message,
             database::commands::get_recent_messages,
+            database::commands::get_messages_page,
+            database::commands::update_message,
             database::commands::delete_message,
+            database::commands::list_message_revisions,
+            database::commands::restore_message_revision,
+            // Trash commands
+            database::commands::list_trash,
+            database::commands::restore_session,
+            database::commands::restore_message,
+            database::commands::purge_trash,
+            // Branch commands
+            database::commands::list_branches,
+            database::commands::switch_branch,
+            database::commands::get_transcript,
+            // Chat commands
+            chat::send_chat_message,
             // Search commands
             database::commands::semantic_search,
             // OpenRouter settings test
@@ -125,11 +165,28 @@ pub fn run() {
             // Settings commands
             settings::save_settings,
             settings::load_settings,
+            // Data directory commands
+            paths::get_data_directory,
+            paths::move_data_directory,
+            // Workspace commands
+            workspaces::list_workspaces,
+            workspaces::get_active_workspace,
+            workspaces::create_workspace,
+            workspaces::switch_workspace,
+            workspaces::rename_workspace,
+            workspaces::delete_workspace,
         ])
         .setup(|app| {
             // Initialize database state
-            let database_state: DatabaseState = Arc::new(Mutex::new(None));
-            app.manage(database_state);
+            let database_state: DatabaseState = Arc::new(RwLock::new(None));
+            database::backup::spawn_backup_scheduler(database_state.clone());
+            app.manage(database_state.clone());
+
+            // Open the configured database without waiting for the frontend to ask
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                database::commands::init_from_settings(&app_handle, &database_state).await;
+            });
 
             // Create tray menu items
             let show = MenuItem::new(app, "Show", true, None::<&str>)?;
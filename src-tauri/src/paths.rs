@@ -0,0 +1,262 @@
+//! Where OpenConverse keeps its data
+//!
+//! Everything lives in one data directory, resolved in this order:
+//!
+//! 1. `OPENCONV_HOME`, when set (also how tests run against a temporary home)
+//! 2. A location chosen with `move_data_directory`, recorded in
+//!    `<config dir>/openconverse/location.json`
+//! 3. `~/.openconv` when it already exists, so older installs keep their data
+//! 4. `$XDG_DATA_HOME/openconverse` on Linux, `~/.openconv` elsewhere
+//!
+//! Workspaces, settings, personas and the database are all laid out beneath it.
+
+use crate::database::commands::{self as database_commands, DatabaseState, DatabaseStatus};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, State};
+
+pub const HOME_ENV: &str = "OPENCONV_HOME";
+
+/// Tests that change the process environment hold this so they don't interleave
+#[cfg(test)]
+pub(crate) static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+const APP_DIR_NAME: &str = "openconverse";
+const LEGACY_DIR_NAME: &str = ".openconv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    Environment, // OPENCONV_HOME
+    Configured,  // Moved with `move_data_directory`
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDirInfo {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Location {
+    data_dir: PathBuf,
+}
+
+/// The user's home directory, or the working directory when it can't be found
+pub fn home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// The data directory and how it was chosen
+pub fn resolve_data_dir() -> DataDirInfo {
+    if let Some(path) = std::env::var_os(HOME_ENV).filter(|value| !value.is_empty()) {
+        return DataDirInfo {
+            path: PathBuf::from(path),
+            source: DataDirSource::Environment,
+        };
+    }
+    if let Some(location) = read_location() {
+        return DataDirInfo {
+            path: location.data_dir,
+            source: DataDirSource::Configured,
+        };
+    }
+    DataDirInfo {
+        path: default_data_dir(),
+        source: DataDirSource::Default,
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    resolve_data_dir().path
+}
+
+/// Where the data directory is when nothing overrides it
+pub fn default_data_dir() -> PathBuf {
+    let legacy = home_dir().join(LEGACY_DIR_NAME);
+    if cfg!(target_os = "linux") && !legacy.exists() {
+        if let Some(data_home) = dirs::data_dir() {
+            return data_home.join(APP_DIR_NAME);
+        }
+    }
+    legacy
+}
+
+pub fn workspaces_dir() -> PathBuf {
+    data_dir().join("workspaces")
+}
+
+/// File recording a moved data directory; kept outside it so it can be found
+fn location_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| home_dir().join(".config"))
+        .join(APP_DIR_NAME)
+        .join("location.json")
+}
+
+fn read_location() -> Option<Location> {
+    let data = fs::read_to_string(location_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn write_location(data_dir: &Path) -> Result<(), String> {
+    let path = location_path();
+
+    // Moving back to the default location needs no record
+    if data_dir == default_data_dir() {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(&Location {
+        data_dir: data_dir.to_path_buf(),
+    })
+    .map_err(|e| format!("Failed to serialize data location: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Move the data directory to `target` and remember the new location
+///
+/// The database must be closed. The directory is renamed when possible and
+/// copied otherwise, e.g. onto another disk; the old copy is removed only once
+/// the new one is complete. Database and backup paths in the workspace settings
+/// that pointed inside the old directory are rewritten to the new one.
+pub fn move_data_dir(target: &Path) -> Result<PathBuf, String> {
+    let current = resolve_data_dir();
+    if current.source == DataDirSource::Environment {
+        return Err(format!("The data directory is set by {} and can't be moved", HOME_ENV));
+    }
+    if !target.is_absolute() {
+        return Err("The new data directory must be an absolute path".to_string());
+    }
+
+    let current = current.path;
+    let target = target.to_path_buf();
+    if target == current {
+        return Ok(target);
+    }
+    if target.starts_with(&current) || current.starts_with(&target) {
+        return Err("The new data directory can't contain or be inside the current one".to_string());
+    }
+    if target.exists() {
+        let empty = fs::read_dir(&target)
+            .map_err(|e| format!("Failed to read {}: {}", target.display(), e))?
+            .next()
+            .is_none();
+        if !empty {
+            return Err(format!("{} already exists and is not empty", target.display()));
+        }
+        fs::remove_dir(&target).map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    if !current.exists() {
+        fs::create_dir_all(&target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    } else if fs::rename(&current, &target).is_err() {
+        // Renames fail across file systems, so copy instead
+        if let Err(e) = copy_dir(&current, &target) {
+            let _ = fs::remove_dir_all(&target);
+            return Err(format!("Failed to copy data to {}: {}", target.display(), e));
+        }
+        fs::remove_dir_all(&current).map_err(|e| {
+            format!("Data was copied to {} but {} couldn't be removed: {}", target.display(), current.display(), e)
+        })?;
+    }
+
+    rewrite_settings_paths(&current, &target)?;
+    write_location(&target)?;
+    Ok(target)
+}
+
+/// Settings holding paths that may point inside the data directory
+const SETTINGS_PATH_POINTERS: [&str; 2] = ["/memory_config/config/path", "/memory_config/config/backup/directory"];
+
+/// Point settings that referred to the old data directory at the new one
+fn rewrite_settings_paths(from: &Path, to: &Path) -> Result<(), String> {
+    let mut settings_files = vec![to.join("settings").join("settings.json")];
+    if let Ok(entries) = fs::read_dir(to.join("workspaces")) {
+        settings_files.extend(entries.flatten().map(|entry| entry.path().join("settings").join("settings.json")));
+    }
+
+    for path in settings_files.into_iter().filter(|path| path.is_file()) {
+        let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let Ok(mut settings) = serde_json::from_str::<serde_json::Value>(&data) else {
+            continue;
+        };
+
+        let mut changed = false;
+        for pointer in SETTINGS_PATH_POINTERS {
+            if let Some(serde_json::Value::String(value)) = settings.pointer_mut(pointer) {
+                if let Ok(rest) = expand_tilde(value.trim()).strip_prefix(from) {
+                    *value = to.join(rest).to_string_lossy().to_string();
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            let json = serde_json::to_string_pretty(&settings)
+                .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+            fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+#[command]
+pub fn get_data_directory() -> Result<DataDirInfo, String> {
+    Ok(resolve_data_dir())
+}
+
+/// Move all data to a new directory and reopen the database from there
+///
+/// An unlocked database stays unlocked. If the move fails, the database is
+/// reopened where it was.
+#[command]
+pub async fn move_data_directory(
+    app: AppHandle,
+    new_path: String,
+    state: State<'_, DatabaseState>,
+) -> Result<DatabaseStatus, String> {
+    let mut state_guard = state.write().await;
+    let passphrase = state_guard
+        .as_ref()
+        .and_then(|manager| manager.passphrase().map(str::to_string));
+    if let Some(manager) = state_guard.take() {
+        manager.close().await;
+    }
+
+    let moved = move_data_dir(&expand_tilde(new_path.trim()));
+    let status = database_commands::open_from_settings(&app, &mut state_guard, passphrase).await;
+    moved.map(|_| status)
+}
@@ -1,8 +1,10 @@
+use crate::database::commands::{self as database_commands, DatabaseState};
+use crate::database::DatabaseConfig;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
-use tauri::command;
+use tauri::{command, AppHandle, State};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProviderConfig {
@@ -22,14 +24,19 @@ pub struct SettingsData {
     pub memory_config: serde_json::Value,
 }
 
+/// Settings of the active workspace
 fn settings_path() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not get home directory");
-    home.join(".openconv/settings/settings.json")
+    crate::workspaces::active_workspace().settings_path()
 }
 
+/// Save the settings, reopening the database when its backend, path or pool changed
 #[command]
-pub fn save_settings(settings: SettingsData) -> Result<(), String> {
-    println!("[Tauri] save_settings called with: {:?}", settings);
+pub async fn save_settings(
+    app: AppHandle,
+    settings: SettingsData,
+    state: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    let previous_memory_config = load_settings().ok().map(|previous| previous.memory_config);
     let path = settings_path();
     if let Some(parent) = path.parent() {
         if !parent.exists() {
@@ -39,10 +46,24 @@ pub fn save_settings(settings: SettingsData) -> Result<(), String> {
     let json = serde_json::to_string_pretty(&settings).map_err(|e| format!("Failed to serialize settings: {}", e))?;
     let mut file = fs::File::create(&path).map_err(|e| format!("Failed to create settings file: {}", e))?;
     file.write_all(json.as_bytes()).map_err(|e| format!("Failed to write settings: {}", e))?;
-    println!("[Tauri] Settings saved successfully to: {:?}", path);
+
+    let database_changed = previous_memory_config.is_none_or(|previous| {
+        database_settings(&previous) != database_settings(&settings.memory_config)
+    });
+    if database_changed {
+        database_commands::init_from_settings(&app, &state).await;
+    }
+
     Ok(())
 }
 
+/// The parts of `memory_config` that require reopening the database
+fn database_settings(memory_config: &serde_json::Value) -> Option<(String, crate::database::PoolOptions)> {
+    DatabaseConfig::from_memory_config(memory_config)
+        .ok()
+        .map(|config| (config.connection_string, config.pool))
+}
+
 #[command]
 pub fn load_settings() -> Result<SettingsData, String> {
     let path = settings_path();
@@ -61,6 +82,5 @@ pub fn load_settings() -> Result<SettingsData, String> {
     
     let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read settings: {}", e))?;
     let settings: SettingsData = serde_json::from_str(&data).map_err(|e| format!("Failed to parse settings: {}", e))?;
-    println!("[Tauri] Settings loaded successfully: {:?}", settings);
     Ok(settings)
 }
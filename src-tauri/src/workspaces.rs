@@ -0,0 +1,295 @@
+//! Named workspaces, each with its own database, attachments and provider settings
+//!
+//! The registry of workspaces and the active one live in `workspaces.json` in
+//! the data directory. Every workspace keeps its files under
+//! `workspaces/<id>/`, laid out like the data directory itself:
+//!
+//! ```text
+//! db/conv.db
+//! attachments/
+//! personas/
+//! settings/settings.json
+//! ```
+//!
+//! The `default` workspace is the data directory, so data from before
+//! workspaces existed stays where it is.
+
+use crate::database::commands::{self as database_commands, DatabaseState, DatabaseStatus};
+use crate::paths;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{command, AppHandle, Emitter, State};
+
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+
+/// Event emitted with the new active `WorkspaceInfo` after a switch
+pub const WORKSPACE_CHANGED_EVENT: &str = "workspace-changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64, // Unix timestamp
+}
+
+impl Workspace {
+    fn default_workspace() -> Self {
+        Self {
+            id: DEFAULT_WORKSPACE_ID.to_string(),
+            name: "Default".to_string(),
+            created_at: Utc::now().timestamp(),
+        }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        if self.id == DEFAULT_WORKSPACE_ID {
+            paths::data_dir()
+        } else {
+            paths::workspaces_dir().join(&self.id)
+        }
+    }
+
+    pub fn db_path(&self) -> PathBuf {
+        self.dir().join("db").join("conv.db")
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.dir().join("settings").join("settings.json")
+    }
+
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.dir().join("attachments")
+    }
+
+    pub fn personas_dir(&self) -> PathBuf {
+        self.dir().join("personas")
+    }
+}
+
+/// A workspace as shown to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub active: bool,
+    pub db_path: PathBuf,
+    pub attachments_dir: PathBuf,
+}
+
+impl WorkspaceInfo {
+    fn new(workspace: &Workspace, active_id: &str) -> Self {
+        Self {
+            id: workspace.id.clone(),
+            name: workspace.name.clone(),
+            created_at: workspace.created_at,
+            active: workspace.id == active_id,
+            db_path: workspace.db_path(),
+            attachments_dir: workspace.attachments_dir(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registry {
+    active: String,
+    workspaces: Vec<Workspace>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_WORKSPACE_ID.to_string(),
+            workspaces: vec![Workspace::default_workspace()],
+        }
+    }
+}
+
+impl Registry {
+    fn load() -> Result<Self, String> {
+        let path = registry_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read workspaces: {}", e))?;
+        let mut registry: Registry =
+            serde_json::from_str(&data).map_err(|e| format!("Failed to parse workspaces: {}", e))?;
+
+        // The default workspace can't be removed, even by editing the file
+        if registry.find(DEFAULT_WORKSPACE_ID).is_none() {
+            registry.workspaces.insert(0, Workspace::default_workspace());
+        }
+        if registry.find(&registry.active).is_none() {
+            registry.active = DEFAULT_WORKSPACE_ID.to_string();
+        }
+        Ok(registry)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = registry_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize workspaces: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write workspaces: {}", e))
+    }
+
+    fn find(&self, id: &str) -> Option<&Workspace> {
+        self.workspaces.iter().find(|workspace| workspace.id == id)
+    }
+
+    fn get(&self, id: &str) -> Result<&Workspace, String> {
+        self.find(id).ok_or_else(|| format!("Workspace '{}' not found", id))
+    }
+
+    fn active(&self) -> &Workspace {
+        self.find(&self.active).unwrap_or(&self.workspaces[0])
+    }
+
+    fn infos(&self) -> Vec<WorkspaceInfo> {
+        self.workspaces
+            .iter()
+            .map(|workspace| WorkspaceInfo::new(workspace, &self.active))
+            .collect()
+    }
+
+    /// Slug of `name` that no other workspace uses
+    fn unique_id(&self, name: &str) -> String {
+        let slug = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>()
+            .split('-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let slug = if slug.is_empty() { "workspace".to_string() } else { slug };
+
+        let mut id = slug.clone();
+        let mut suffix = 2;
+        while self.find(&id).is_some() || paths::workspaces_dir().join(&id).exists() {
+            id = format!("{}-{}", slug, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+fn registry_path() -> PathBuf {
+    paths::data_dir().join("workspaces.json")
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name must not be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// The active workspace; the default one when the registry can't be read
+pub fn active_workspace() -> Workspace {
+    Registry::load()
+        .map(|registry| registry.active().clone())
+        .unwrap_or_else(|_| Workspace::default_workspace())
+}
+
+#[command]
+pub fn list_workspaces() -> Result<Vec<WorkspaceInfo>, String> {
+    Ok(Registry::load()?.infos())
+}
+
+#[command]
+pub fn get_active_workspace() -> Result<WorkspaceInfo, String> {
+    let registry = Registry::load()?;
+    Ok(WorkspaceInfo::new(registry.active(), &registry.active))
+}
+
+/// Create an empty workspace; it becomes active only once switched to
+#[command]
+pub fn create_workspace(name: String) -> Result<WorkspaceInfo, String> {
+    let name = validate_name(&name)?;
+    let mut registry = Registry::load()?;
+    let workspace = Workspace {
+        id: registry.unique_id(&name),
+        name,
+        created_at: Utc::now().timestamp(),
+    };
+
+    for dir in [
+        workspace.attachments_dir(),
+        workspace.dir().join("db"),
+        workspace.dir().join("settings"),
+    ] {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    registry.workspaces.push(workspace.clone());
+    registry.save()?;
+    Ok(WorkspaceInfo::new(&workspace, &registry.active))
+}
+
+/// Make a workspace active and open its database
+///
+/// The choice is saved first, so the workspace is still active after a restart
+/// even when its database is locked or fails to open now.
+#[command]
+pub async fn switch_workspace(
+    app: AppHandle,
+    workspace_id: String,
+    state: State<'_, DatabaseState>,
+) -> Result<DatabaseStatus, String> {
+    let mut registry = Registry::load()?;
+    let info = WorkspaceInfo::new(registry.get(&workspace_id)?, &workspace_id);
+    registry.active = workspace_id;
+    registry.save()?;
+
+    let status = database_commands::init_from_settings(&app, &state).await;
+    let _ = app.emit(WORKSPACE_CHANGED_EVENT, &info);
+    Ok(status)
+}
+
+#[command]
+pub fn rename_workspace(workspace_id: String, name: String) -> Result<WorkspaceInfo, String> {
+    let name = validate_name(&name)?;
+    let mut registry = Registry::load()?;
+    let workspace = registry
+        .workspaces
+        .iter_mut()
+        .find(|workspace| workspace.id == workspace_id)
+        .ok_or_else(|| format!("Workspace '{}' not found", workspace_id))?;
+    workspace.name = name;
+    let workspace = workspace.clone();
+
+    registry.save()?;
+    Ok(WorkspaceInfo::new(&workspace, &registry.active))
+}
+
+/// Delete a workspace and all of its files
+///
+/// The default and the active workspace can't be deleted; switch away first.
+#[command]
+pub fn delete_workspace(workspace_id: String) -> Result<bool, String> {
+    if workspace_id == DEFAULT_WORKSPACE_ID {
+        return Err("The default workspace can't be deleted".to_string());
+    }
+    let mut registry = Registry::load()?;
+    if registry.active == workspace_id {
+        return Err("The active workspace can't be deleted".to_string());
+    }
+    let Some(index) = registry.workspaces.iter().position(|workspace| workspace.id == workspace_id) else {
+        return Ok(false);
+    };
+
+    let workspace = registry.workspaces.remove(index);
+    registry.save()?;
+
+    let dir = workspace.dir();
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete {}: {}", dir.display(), e))?;
+    }
+    Ok(true)
+}
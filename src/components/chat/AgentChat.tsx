@@ -40,10 +40,11 @@ const AgentChat: React.FC<AgentChatProps> = ({
 
   // Initialize chat hook
   const { sendMessage, isLoading, error, clearError } = useChat({
-    onSuccess: (response, agentType) => {
+    onSuccess: (response, agentType, reasoning) => {
       const aiMessage: ChatMessage = {
         id: Date.now().toString(),
         content: response,
+        reasoning,
         sender: 'ai',
         timestamp: Date.now(),
       };
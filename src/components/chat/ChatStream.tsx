@@ -138,6 +138,21 @@ const ChatStream: React.FC<ChatStreamProps> = ({ messages }) => {
                 )}
                 
                 <Box flex={1}>
+                  {msg.reasoning && (
+                    <Text
+                      mb={2}
+                      pl={3}
+                      fontSize="sm"
+                      fontStyle="italic"
+                      color="gray.500"
+                      borderLeft="2px solid"
+                      borderColor="gray.200"
+                      whiteSpace="pre-wrap"
+                      className="chat-message-reasoning"
+                    >
+                      {msg.reasoning}
+                    </Text>
+                  )}
                   <Text 
                     lineHeight="tall"
                     fontSize="md"
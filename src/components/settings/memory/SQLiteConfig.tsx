@@ -31,11 +31,12 @@ export default function SQLiteConfig({ onUpdateConfig }: SQLiteConfigProps) {
         console.log('Database init result:', initError);
       }
       
-      const stats = await invoke<DatabaseStats>('get_database_stats');
-      setDatabaseStats(stats);
-      
+      // Resolved by the backend, so it is known even while the database is locked
       const path = await invoke<string>('get_database_path');
       setDatabasePath(path);
+      
+      const stats = await invoke<DatabaseStats>('get_database_stats');
+      setDatabaseStats(stats);
       setIsDatabaseInitialized(true);
     } catch (error) {
       console.error('Failed to load database stats:', error);
@@ -69,9 +70,6 @@ export default function SQLiteConfig({ onUpdateConfig }: SQLiteConfigProps) {
   const initializeDatabase = async () => {
     setIsMemoryLoading(true);
     try {
-      await new Promise(resolve => setTimeout(resolve, 1000));
-      const path = '~/.openconv/settings/db/conv.db';
-      setDatabasePath(path);
       await loadDatabaseStats();
       setIsDatabaseInitialized(true);
       setMemoryOperationMessage('Database initialized successfully');
@@ -123,7 +121,7 @@ export default function SQLiteConfig({ onUpdateConfig }: SQLiteConfigProps) {
             Database Location
           </Text>
           <Text fontSize="xs" color="blue.700">
-            {databasePath || '~/.openconv/settings/db/conv.db'}
+            {databasePath || 'Resolving...'}
           </Text>
         </Box>
 
@@ -2,16 +2,22 @@ import { useState, useCallback } from 'react';
 import { AgentFactory } from '@/agents';
 import { readSettings } from '@/utils/settings';
 import { MessageMemoryProvider } from '../../shared/memory';
+import { messageToChatMessage } from '../../shared/langchain-adapters';
+import { isTauri, streamChatMessage } from '@/utils/tauri';
 import type { AgentType } from '@/agents';
 
 interface UseChatOptions {
-  onSuccess?: (response: string, agentType: AgentType) => void;
+  onSuccess?: (response: string, agentType: AgentType, reasoning?: string) => void;
   onError?: (error: string) => void;
+  onContent?: (text: string) => void; // Answer increment while the reply streams
+  onReasoning?: (text: string) => void; // Reasoning increment, kept apart from the answer
 }
 
 export function useChat(options: UseChatOptions = {}) {
   const [isLoading, setIsLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [streamingContent, setStreamingContent] = useState('');
+  const [streamingReasoning, setStreamingReasoning] = useState('');
   const memoryProvider = new MessageMemoryProvider();
 
   // Fetch chat history for a session
@@ -39,8 +45,28 @@ export function useChat(options: UseChatOptions = {}) {
 
     setIsLoading(true);
     setError(null);
+    setStreamingContent('');
+    setStreamingReasoning('');
 
     try {
+      // In the app the backend saves both turns and streams the reply using the
+      // session's own role, goals, model and generation parameters
+      if (isTauri()) {
+        const reply = await streamChatMessage(sessionId, message.trim(), {
+          onContent: (text) => {
+            setStreamingContent((prev) => prev + text);
+            options.onContent?.(text);
+          },
+          onReasoning: (text) => {
+            setStreamingReasoning((prev) => prev + text);
+            options.onReasoning?.(text);
+          },
+        });
+        const { content, reasoning } = messageToChatMessage(reply);
+        options.onSuccess?.(content, agentType, reasoning);
+        return content;
+      }
+
       // Load settings and create agent directly
       const settings = await readSettings();
       // Validate settings
@@ -91,5 +117,7 @@ export function useChat(options: UseChatOptions = {}) {
     error,
     clearError,
     fetchHistory,
+    streamingContent,
+    streamingReasoning,
   };
 }
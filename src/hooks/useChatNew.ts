@@ -1,9 +1,13 @@
 import { useState, useCallback } from 'react';
+import { messageToChatMessage } from '../../shared/langchain-adapters';
+import { isTauri, streamChatMessage } from '@/utils/tauri';
 import type { AgentType, ChatRequest, ChatResponse } from '@/agents';
 
 interface UseChatOptions {
-  onSuccess?: (response: string, agentType: AgentType) => void;
+  onSuccess?: (response: string, agentType: AgentType, reasoning?: string) => void;
   onError?: (error: string) => void;
+  onContent?: (text: string) => void; // Answer increment while the reply streams
+  onReasoning?: (text: string) => void; // Reasoning increment, kept apart from the answer
 }
 
 export function useChat(options: UseChatOptions = {}) {
@@ -27,6 +31,17 @@ export function useChat(options: UseChatOptions = {}) {
     setError(null);
 
     try {
+      // In the app the reply comes from the backend, which also saves both turns
+      if (isTauri()) {
+        const reply = await streamChatMessage(sessionId, message.trim(), {
+          onContent: options.onContent,
+          onReasoning: options.onReasoning,
+        });
+        const { content, reasoning } = messageToChatMessage(reply);
+        options.onSuccess?.(content, agentType, reasoning);
+        return content;
+      }
+
       const requestBody: ChatRequest = {
         message: message.trim(),
         sessionId,
@@ -9,13 +9,15 @@ import useSessionMessages from '@/hooks/useSessionMessages';
 import { AgentFactory } from '@/agents';
 import { readSettings } from '@/utils/settings';
 import { chatMessageToCreateMessage, agentSessionManager } from '@shared/langchain-adapters';
-import { tauriCommands } from '@/utils/tauri';
+import { tauriCommands, isTauri, streamChatMessage } from '@/utils/tauri';
 import type { ChatMessage, SettingsData } from '@shared/types';
 
 export default function Home() {
   const { sessions, loading, createSession } = useSessions();
   const [activeId, setActiveId] = useState<string>(''); // Start with no active session
   const { messages, loading: messagesLoading, addMessage, loadMessages } = useSessionMessages();
+  // Turns shown while a reply streams, until the saved ones are reloaded
+  const [pendingMessages, setPendingMessages] = useState<ChatMessage[]>([]);
   const [isSidebarCollapsed, setIsSidebarCollapsed] = useState(false); // Default expanded on desktop
   const [settings, setSettings] = useState<SettingsData | null>(null);
   const [isAgentConfigured, setIsAgentConfigured] = useState(false);
@@ -54,6 +56,7 @@ export default function Home() {
     if (activeId) {
       const sessionId = parseInt(activeId);
       console.log('Loading messages for session:', sessionId, 'activeId:', activeId);
+      setPendingMessages([]);
       loadMessages(sessionId);
     }
   }, [activeId, loadMessages]);
@@ -91,8 +94,40 @@ export default function Home() {
       timestamp: Date.now() 
     };
     
-    // Add user message to database and local state
     const sessionIdNum = parseInt(currentSessionId);
+
+    // In the app the backend saves both turns and streams the reply with the
+    // session's role, goals, model and generation parameters
+    if (isTauri()) {
+      const replyId = (Date.now() + 1).toString();
+      const updateReply = (update: (reply: ChatMessage) => ChatMessage) =>
+        setPendingMessages(prev => prev.map(m => (m.id === replyId ? update(m) : m)));
+
+      setPendingMessages([
+        newUserMessage,
+        { id: replyId, sender: 'ai', content: '', timestamp: Date.now() },
+      ]);
+      try {
+        await streamChatMessage(sessionIdNum, msg, {
+          onContent: text => updateReply(m => ({ ...m, content: m.content + text })),
+          onReasoning: text => updateReply(m => ({ ...m, reasoning: (m.reasoning ?? '') + text })),
+        });
+        await loadMessages(sessionIdNum);
+        setPendingMessages([]);
+      } catch (error) {
+        console.error('Chat error:', error);
+        await loadMessages(sessionIdNum);
+        setPendingMessages([{
+          id: replyId,
+          sender: 'ai',
+          content: `Sorry, I encountered an error: ${error instanceof Error ? error.message : String(error)}`,
+          timestamp: Date.now(),
+        }]);
+      }
+      return;
+    }
+
+    // Add user message to database and local state
     await addMessage(newUserMessage, sessionIdNum);
 
     // Use agent system if configured, otherwise fallback to mock response
@@ -162,7 +197,7 @@ export default function Home() {
     }
   };
 
-  const currentMessages = messages;
+  const currentMessages = [...messages, ...pendingMessages];
   const hasMessages = currentMessages.length > 0;
 
   // Backgrounds for each conversation (session)
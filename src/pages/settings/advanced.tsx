@@ -67,7 +67,7 @@ export default function AdvancedPage() {
     try {
       // Initialize database first if not already initialized
       try {
-        await invoke('init_database', { database_path: null });
+        await invoke('init_database', { databasePath: null });
       } catch (initError) {
         // Database might already be initialized, continue
         console.log('Database init result:', initError);
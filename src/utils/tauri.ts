@@ -1,5 +1,6 @@
 import { getCurrentWindow } from '@tauri-apps/api/window';
-import type { Session, CreateSession, Message, CreateMessage } from '@shared/database-types';
+import type { Session, CreateSession, Message, MessageRole, CreateMessage, DatabaseStatus, ChatStreamEvent } from '@shared/database-types';
+import type { DataDirInfo, WorkspaceInfo } from '@shared/types';
 
 /**
  * Check if we're running in a Tauri environment
@@ -136,7 +137,7 @@ export const tauriCommands = {
   // Message commands
   async saveMessage(
     sessionId: number, 
-    role: string, 
+    role: MessageRole, 
     content: string, 
     embedding?: number[], 
     recallScore?: number
@@ -169,10 +170,72 @@ export const tauriCommands = {
     return await safeInvoke('delete_message', { message_id: messageId }) as boolean;
   },
 
+  // Chat commands
+  // Saves the user message and the reply; increments arrive as `chat-stream` events
+  async sendChatMessage(sessionId: number, content: string): Promise<Message> {
+    if (typeof window === 'undefined') throw new Error('Chat not available in SSR');
+    return await safeInvoke('send_chat_message', {
+      params: { sessionId, content }
+    }) as Message;
+  },
+
+  async onChatStream(handler: (event: ChatStreamEvent) => void): Promise<() => void> {
+    if (typeof window === 'undefined') return () => {};
+    const { listen } = await import('@tauri-apps/api/event');
+    return await listen<ChatStreamEvent>('chat-stream', (event) => handler(event.payload));
+  },
+
   // Database commands
   async initDatabase(databasePath?: string): Promise<string> {
     if (typeof window === 'undefined') return 'Database not available in SSR';
-    return await safeInvoke('init_database', { database_path: databasePath }) as string;
+    return await safeInvoke('init_database', { databasePath }) as string;
+  },
+
+  async getDatabaseStatus(): Promise<DatabaseStatus> {
+    if (typeof window === 'undefined') return { state: 'uninitialized' };
+    return await safeInvoke('get_database_status') as DatabaseStatus;
+  },
+
+  async unlockDatabase(passphrase: string, databasePath?: string): Promise<string> {
+    if (typeof window === 'undefined') return 'Database not available in SSR';
+    return await safeInvoke('unlock_database', { passphrase, databasePath }) as string;
+  },
+
+  // Data directory commands
+  async getDataDirectory(): Promise<DataDirInfo> {
+    if (typeof window === 'undefined') throw new Error('Data directory not available in SSR');
+    return await safeInvoke('get_data_directory') as DataDirInfo;
+  },
+
+  async moveDataDirectory(newPath: string): Promise<DatabaseStatus> {
+    if (typeof window === 'undefined') throw new Error('Data directory not available in SSR');
+    return await safeInvoke('move_data_directory', { newPath }) as DatabaseStatus;
+  },
+
+  // Workspace commands
+  async listWorkspaces(): Promise<WorkspaceInfo[]> {
+    if (typeof window === 'undefined') return [];
+    return await safeInvoke('list_workspaces') as WorkspaceInfo[];
+  },
+
+  async createWorkspace(name: string): Promise<WorkspaceInfo> {
+    if (typeof window === 'undefined') throw new Error('Workspaces not available in SSR');
+    return await safeInvoke('create_workspace', { name }) as WorkspaceInfo;
+  },
+
+  async switchWorkspace(workspaceId: string): Promise<DatabaseStatus> {
+    if (typeof window === 'undefined') throw new Error('Workspaces not available in SSR');
+    return await safeInvoke('switch_workspace', { workspaceId }) as DatabaseStatus;
+  },
+
+  async renameWorkspace(workspaceId: string, name: string): Promise<WorkspaceInfo> {
+    if (typeof window === 'undefined') throw new Error('Workspaces not available in SSR');
+    return await safeInvoke('rename_workspace', { workspaceId, name }) as WorkspaceInfo;
+  },
+
+  async deleteWorkspace(workspaceId: string): Promise<boolean> {
+    if (typeof window === 'undefined') return false;
+    return await safeInvoke('delete_workspace', { workspaceId }) as boolean;
   },
 
   async clearAllMemory(): Promise<string> {
@@ -194,6 +257,32 @@ export const tauriCommands = {
   }
 };
 
+export interface ChatStreamHandlers {
+  onContent?: (text: string) => void;
+  onReasoning?: (text: string) => void;
+}
+
+/**
+ * Send a chat message through the backend, passing this session's stream
+ * increments to the handlers until the saved reply is returned
+ */
+export async function streamChatMessage(
+  sessionId: number,
+  content: string,
+  handlers: ChatStreamHandlers = {}
+): Promise<Message> {
+  const unlisten = await tauriCommands.onChatStream((event) => {
+    if (event.session_id !== sessionId) return;
+    if (event.type === 'content') handlers.onContent?.(event.text);
+    if (event.type === 'reasoning') handlers.onReasoning?.(event.text);
+  });
+  try {
+    return await tauriCommands.sendChatMessage(sessionId, content);
+  } finally {
+    unlisten();
+  }
+}
+
 // Window management utilities
 export const windowUtils = {
   async minimize() {